@@ -1,19 +1,127 @@
 //! 应用主模块，负责初始化和运行
 
-use std::sync::{Arc, Mutex};
-
 // 声明子模块
+mod cli;
+mod clock;
+mod config;
+mod controller;
+mod expiry;
+#[cfg(windows)]
 mod darkmode;
+#[cfg(target_os = "linux")]
+mod headless;
+// 菜单文本相关的部分只在 Windows 托盘中使用
+#[cfg_attr(not(windows), allow(dead_code))]
 mod i18n;
+mod notify;
+mod power;
+mod reducer;
+mod rules;
+mod session;
+#[cfg_attr(not(windows), allow(dead_code))]
 mod state;
+#[cfg(all(test, target_os = "linux"))]
+mod testbus;
+#[cfg(all(test, target_os = "linux"))]
+mod testdisplay;
 mod timer;
+#[cfg(windows)]
 mod tray;
+mod wait;
+#[cfg(windows)]
 mod win_api;
 
+use config::Config;
+use power::PowerBackend;
 use state::AppState;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 按配置创建启动时的状态，之后再按 `startup` 恢复会话
+fn initial_state(config: &Config) -> AppState {
+    let mut state = AppState::new();
+    state.mode = config.mode;
+    state.duration_options = state::duration_options(&config.durations);
+    state.until_times = config.until.clone();
+    state.warn_before_minutes = config.warn_before_minutes;
+    state.expiry_action = config.expiry_action;
+    state.expiry_command = config.expiry_command.clone();
+    state.missed_expiry = config.missed_expiry;
+    state.rules = config
+        .rules
+        .iter()
+        .map(|rule| state::RuleStatus {
+            label: rules::build(rule).label(&state.translations),
+            matched: false,
+        })
+        .collect();
+    state
+}
+
+/// 按配置探测后端，需要时在外层加上模拟输入
+fn create_backend(config: &Config) -> Box<dyn PowerBackend> {
+    let backend = Box::new(power::detect(&config.backends));
+    let Some(interval) = config.jiggle_interval_secs else {
+        return backend;
+    };
+    match power::default_injector() {
+        Ok(injector) => {
+            eprintln!("已启用模拟输入，间隔 {} 秒", interval);
+            Box::new(power::JigglerBackend::new(backend, injector, Duration::from_secs(interval.max(1))))
+        }
+        Err(e) => {
+            eprintln!("无法模拟输入: {}", e);
+            backend
+        }
+    }
+}
+
+/// 命令行要求运行命令或等待进程时执行完再退出，否则返回，继续常驻运行
+fn run_cli_command() {
+    match cli::parse(std::env::args_os().skip(1)) {
+        Ok(cli::Mode::Resident) => {}
+        result => run_cli_mode(result),
+    }
+}
+
+/// 命令行程序的入口，没有给出命令时打印用法
+///
+/// 命令行程序使用控制台子系统，cmd.exe 和 PowerShell 会等待它结束并取得退出码。
+pub fn run_cli() {
+    match cli::parse(std::env::args_os().skip(1)) {
+        Ok(cli::Mode::Resident) => run_cli_mode(Err(cli::USAGE.to_string())),
+        result => run_cli_mode(result),
+    }
+}
+
+/// 运行命令或等待进程，然后以相应的退出码退出
+fn run_cli_mode(mode: Result<cli::Mode, String>) -> ! {
+    // 托盘程序的发布版本没有控制台，需要连接到启动本程序的终端才能继承输入输出
+    #[cfg(windows)]
+    win_api::attach_parent_console();
+    let mode = match mode {
+        Ok(mode) => mode,
+        Err(usage) => {
+            eprintln!("{}", usage);
+            std::process::exit(2);
+        }
+    };
+    let config = config::load();
+    let mut backend = create_backend(&config);
+    let code = match mode {
+        cli::Mode::Resident => unreachable!("常驻运行不经过命令行模式"),
+        cli::Mode::Run(command) => cli::run_command(backend.as_mut(), config.mode, &command),
+        cli::Mode::WaitPids(pids) => cli::wait_pids(backend.as_mut(), config.mode, &pids),
+    };
+    std::process::exit(code);
+}
 
 /// 运行应用程序
+#[cfg(windows)]
 pub fn run() {
+    // 0. 命令行模式不需要托盘，也不受单实例限制
+    run_cli_command();
+
     // 1. 确保只有一个实例在运行
     if !win_api::create_single_instance_mutex() {
         return;
@@ -22,12 +130,36 @@ pub fn run() {
     // 2. 初始化暗色模式支持
     darkmode::init_dark_mode();
 
-    // 3. 初始化应用状态 (这会加载语言文件)
-    let app_state = Arc::new(Mutex::new(AppState::new()));
+    // 3. 初始化应用状态 (这会加载语言文件和配置)
+    let config = config::load();
+    let mut state = initial_state(&config);
+    let mut session_store = session::SessionStore::open_default();
+    let saved = session_store.as_mut().and_then(|store| store.load());
+    session::restore(&mut state, config.startup, saved, chrono::Utc::now());
+    let rules: Vec<_> = config.rules.iter().map(rules::build).collect();
+    let app_state = Arc::new(Mutex::new(state));
+
+    // 4. 探测可用的电源管理后端
+    let backend = create_backend(&config);
 
-    // 4. 创建托盘图标并启动事件循环
-    tray::run_tray_event_loop(app_state);
+    // 5. 创建托盘图标并启动事件循环
+    tray::run_tray_event_loop(app_state, backend, session_store, rules);
 
-    // 5. 运行 Windows 消息循环
+    // 6. 运行 Windows 消息循环
     win_api::message_loop();
 }
+
+/// 运行应用程序（Linux 下没有托盘，通过信号控制）
+#[cfg(target_os = "linux")]
+pub fn run() {
+    run_cli_command();
+    let config = config::load();
+    let backend = create_backend(&config);
+    let mut state = initial_state(&config);
+    let mut session_store = session::SessionStore::open_default();
+    let saved = session_store.as_mut().and_then(|store| store.load());
+    session::restore(&mut state, config.startup, saved, chrono::Utc::now());
+    let rules: Vec<_> = config.rules.iter().map(rules::build).collect();
+    let app_state = Arc::new(Mutex::new(state));
+    headless::run_headless_event_loop(app_state, backend, session_store, rules);
+}
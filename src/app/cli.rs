@@ -0,0 +1,284 @@
+//! 命令行模式
+//!
+//! `keep-screen-cli run -- <命令>` 在命令运行期间保持唤醒，不创建托盘图标，
+//! 可以在 SSH 会话和脚本中使用。命令的标准输入输出直接继承，退出码原样返回。
+//! `keep-screen-cli --wait-pid <PID>...` 保持唤醒直到已经在运行的进程全部退出。
+
+use super::power::PowerBackend;
+use super::state::KeepMode;
+use super::wait::ProcessHandle;
+use std::ffi::OsString;
+use std::process::{Command, ExitStatus};
+
+/// 命令行指定的运行方式
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Mode {
+    /// 常驻运行，Windows 上显示托盘图标
+    Resident,
+    /// 运行命令，结束后退出
+    Run(Vec<OsString>),
+    /// 等待这些进程全部退出后退出
+    WaitPids(Vec<u32>),
+}
+
+pub const USAGE: &str = "用法: keep-screen-cli run -- <命令> [参数...]\n      keep-screen-cli --wait-pid <PID>...";
+
+/// 解析命令行参数，不含程序名
+///
+/// 不认识的参数按常驻运行处理，以免影响自启动等已有的调用方式。
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Mode, String> {
+    let mut args = args.into_iter().peekable();
+    if args.peek().is_some_and(|arg| arg == "--wait-pid") {
+        return parse_pids(args);
+    }
+    if args.peek().is_none_or(|arg| arg != "run") {
+        return Ok(Mode::Resident);
+    }
+    args.next();
+    // `--` 可以省略，但写上可以避免命令的参数被误认为本程序的参数
+    if args.peek().is_some_and(|arg| arg == "--") {
+        args.next();
+    }
+    let command: Vec<OsString> = args.collect();
+    if command.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    Ok(Mode::Run(command))
+}
+
+/// 解析 `--wait-pid` 之后的进程号，`--wait-pid` 可以重复出现
+fn parse_pids(args: impl Iterator<Item = OsString>) -> Result<Mode, String> {
+    let mut pids = Vec::new();
+    for arg in args.filter(|arg| arg != "--wait-pid") {
+        match arg.to_str().and_then(|arg| arg.parse::<u32>().ok()) {
+            Some(pid) => pids.push(pid),
+            None => return Err(format!("无效的进程号: {}\n{}", arg.to_string_lossy(), USAGE)),
+        }
+    }
+    if pids.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    Ok(Mode::WaitPids(pids))
+}
+
+/// 保持唤醒并运行命令，返回应当使用的退出码
+///
+/// 申请失败时仍然运行命令，只打印警告。命令无法启动时按 shell 的惯例返回 127 或 126。
+pub fn run_command(backend: &mut dyn PowerBackend, mode: KeepMode, command: &[OsString]) -> i32 {
+    let reason = format!("Keep Screen is running {}", command[0].to_string_lossy());
+    if let Err(e) = backend.acquire(mode, &reason) {
+        eprintln!("{} 后端申请保持唤醒失败，命令仍会运行: {}", backend.name(), e);
+    }
+
+    let code = match Command::new(&command[0]).args(&command[1..]).spawn() {
+        Ok(mut child) => {
+            // 子进程结束后离开这个分支时停止转发
+            let _forwarder = forward_signals(child.id());
+            match child.wait() {
+                Ok(status) => exit_code(status),
+                Err(e) => {
+                    eprintln!("等待命令结束失败: {}", e);
+                    1
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("无法运行 {}: {}", command[0].to_string_lossy(), e);
+            if e.kind() == std::io::ErrorKind::NotFound { 127 } else { 126 }
+        }
+    };
+
+    if let Err(e) = backend.release() {
+        eprintln!("{} 后端释放保持唤醒失败: {}", backend.name(), e);
+    }
+    code
+}
+
+/// 保持唤醒直到这些进程全部退出，返回应当使用的退出码
+///
+/// 先打开所有进程再申请，无法打开的进程只打印错误，其余的照常等待，最后返回 1。
+pub fn wait_pids(backend: &mut dyn PowerBackend, mode: KeepMode, pids: &[u32]) -> i32 {
+    let mut processes = Vec::new();
+    for &pid in pids {
+        match ProcessHandle::open(pid) {
+            Ok(process) => processes.push(process),
+            Err(e) => eprintln!("无法等待进程 {}: {}", pid, e),
+        }
+    }
+    let code = if processes.len() == pids.len() { 0 } else { 1 };
+    if processes.is_empty() {
+        return code;
+    }
+
+    let pids: Vec<String> = processes.iter().map(|process| process.pid.to_string()).collect();
+    let reason = format!("Keep Screen is waiting for PID {}", pids.join(", "));
+    if let Err(e) = backend.acquire(mode, &reason) {
+        eprintln!("{} 后端申请保持唤醒失败，仍会等待进程退出: {}", backend.name(), e);
+    }
+    // 需要全部退出，依次等待即可
+    for process in &processes {
+        if let Err(e) = process.wait() {
+            eprintln!("等待进程 {} 退出失败: {}", process.pid, e);
+        }
+    }
+    if let Err(e) = backend.release() {
+        eprintln!("{} 后端释放保持唤醒失败: {}", backend.name(), e);
+    }
+    code
+}
+
+/// 子进程的退出码，被信号终止时按 shell 的惯例返回 128 加信号编号
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return 128 + signal;
+    }
+    status.code().unwrap_or(1)
+}
+
+/// 把发给本进程的信号转发给子进程，丢弃时停止转发
+#[cfg(unix)]
+struct Forwarder(signal_hook::iterator::Handle);
+
+#[cfg(unix)]
+impl Drop for Forwarder {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+/// 开始转发信号
+///
+/// 终端按下 Ctrl+C 等产生的信号由内核发给整个前台进程组，子进程已经收到，不再重复转发。
+#[cfg(unix)]
+fn forward_signals(pid: u32) -> Option<Forwarder> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGQUIT, SIGTERM, SIGUSR1, SIGUSR2};
+    use signal_hook::iterator::exfiltrator::WithOrigin;
+    use signal_hook::iterator::SignalsInfo;
+    use signal_hook::low_level::siginfo::Cause;
+
+    let mut signals = match SignalsInfo::<WithOrigin>::new([SIGHUP, SIGINT, SIGQUIT, SIGTERM, SIGUSR1, SIGUSR2]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            eprintln!("注册信号处理失败: {}", e);
+            return None;
+        }
+    };
+    let forwarder = Forwarder(signals.handle());
+    std::thread::spawn(move || {
+        for origin in signals.forever() {
+            if origin.cause != Cause::Kernel {
+                unsafe { libc::kill(pid as libc::pid_t, origin.signal) };
+            }
+        }
+    });
+    Some(forwarder)
+}
+
+/// Windows 上控制台的 Ctrl+C 同样会发给子进程，本进程忽略它，等子进程结束后再释放
+#[cfg(windows)]
+struct Forwarder;
+
+#[cfg(windows)]
+fn forward_signals(_pid: u32) -> Option<Forwarder> {
+    super::win_api::ignore_console_interrupts();
+    Some(Forwarder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::app::power::recording::{Call, RecordingBackend};
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn parses_run_command() {
+        assert_eq!(parse(args(&[])), Ok(Mode::Resident));
+        assert_eq!(parse(args(&["--autostart"])), Ok(Mode::Resident));
+        assert_eq!(
+            parse(args(&["run", "--", "cargo", "build", "--release"])),
+            Ok(Mode::Run(args(&["cargo", "build", "--release"])))
+        );
+        // `--` 之后的 `--` 属于命令
+        assert_eq!(parse(args(&["run", "--", "--"])), Ok(Mode::Run(args(&["--"]))));
+        assert_eq!(parse(args(&["run", "make"])), Ok(Mode::Run(args(&["make"]))));
+        assert!(parse(args(&["run", "--"])).is_err());
+    }
+
+    #[test]
+    fn parses_wait_pids() {
+        assert_eq!(parse(args(&["--wait-pid", "12", "34"])), Ok(Mode::WaitPids(vec![12, 34])));
+        assert_eq!(
+            parse(args(&["--wait-pid", "12", "--wait-pid", "34"])),
+            Ok(Mode::WaitPids(vec![12, 34]))
+        );
+        assert!(parse(args(&["--wait-pid"])).is_err());
+        assert!(parse(args(&["--wait-pid", "abc"])).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn holds_until_all_processes_exit() {
+        // 在创建子进程之前计时，子进程全部退出不会早于此后 300ms
+        let start = std::time::Instant::now();
+        let mut children: Vec<_> = ["0.1", "0.3"]
+            .iter()
+            .map(|secs| std::process::Command::new("sleep").arg(secs).spawn().unwrap())
+            .collect();
+        let pids: Vec<u32> = children.iter().map(|child| child.id()).collect();
+        // 子进程由测试回收，等待的一方只看 pidfd
+        let reaper = std::thread::spawn(move || children.iter_mut().for_each(|child| drop(child.wait())));
+
+        let mut backend = RecordingBackend::new();
+        assert_eq!(wait_pids(&mut backend, KeepMode::SystemOnly, &pids), 0);
+        assert!(start.elapsed() >= std::time::Duration::from_millis(300));
+        assert_eq!(
+            backend.calls(),
+            vec![
+                Call::Acquire(
+                    KeepMode::SystemOnly,
+                    format!("Keep Screen is waiting for PID {}, {}", pids[0], pids[1])
+                ),
+                Call::Release,
+            ]
+        );
+        reaper.join().unwrap();
+
+        // 进程都已不存在时不申请
+        let mut backend = RecordingBackend::new();
+        assert_eq!(wait_pids(&mut backend, KeepMode::SystemOnly, &pids), 1);
+        assert!(backend.calls().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn holds_while_command_runs_and_propagates_exit_code() {
+        let mut backend = RecordingBackend::new();
+        let code = run_command(&mut backend, KeepMode::SystemOnly, &args(&["sh", "-c", "exit 3"]));
+        assert_eq!(code, 3);
+        assert_eq!(
+            backend.calls(),
+            vec![
+                Call::Acquire(KeepMode::SystemOnly, "Keep Screen is running sh".to_string()),
+                Call::Release,
+            ]
+        );
+        assert_eq!(backend.query(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signal_exit_and_missing_command() {
+        let mut backend = RecordingBackend::new();
+        let code = run_command(&mut backend, KeepMode::DisplayAndSystem, &args(&["sh", "-c", "kill -TERM $$"]));
+        assert_eq!(code, 128 + libc::SIGTERM);
+
+        let code = run_command(&mut backend, KeepMode::DisplayAndSystem, &args(&["keep-screen-no-such-command"]));
+        assert_eq!(code, 127);
+        assert_eq!(backend.calls().last(), Some(&Call::Release));
+    }
+}
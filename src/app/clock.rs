@@ -0,0 +1,200 @@
+//! 时间相关的计算
+
+use chrono::{DateTime, LocalResult, NaiveTime, TimeDelta, TimeZone, Timelike, Utc};
+use std::time::{Duration, Instant};
+
+/// 处理事件时的时间，同时包含单调时钟和本地时间
+#[derive(Clone, Copy, Debug)]
+pub struct Now {
+    pub instant: Instant,
+    pub wall: DateTime<chrono::Local>,
+}
+
+impl Now {
+    pub fn current() -> Self {
+        Now {
+            instant: Instant::now(),
+            wall: chrono::Local::now(),
+        }
+    }
+
+    /// 两种时间同时前进 `duration`
+    #[cfg(test)]
+    pub fn advanced(self, duration: Duration) -> Self {
+        Now {
+            instant: self.instant + duration,
+            wall: self.wall + TimeDelta::from_std(duration).unwrap(),
+        }
+    }
+
+    /// 模拟系统睡眠了 `duration`：本地时间前进，单调时钟不变
+    #[cfg(test)]
+    pub fn slept(self, duration: Duration) -> Self {
+        Now {
+            instant: self.instant,
+            wall: self.wall + TimeDelta::from_std(duration).unwrap(),
+        }
+    }
+
+    /// 到 `deadline` 还有多久，已过时为零
+    pub fn until(&self, deadline: DateTime<Utc>) -> Duration {
+        deadline.signed_duration_since(self.wall).to_std().unwrap_or(Duration::ZERO)
+    }
+
+    /// 已经超过 `deadline` 多久，未到时为零
+    pub fn since(&self, deadline: DateTime<Utc>) -> Duration {
+        self.wall.signed_duration_since(deadline).to_std().unwrap_or(Duration::ZERO)
+    }
+
+    /// 自 `earlier` 以来本地时间比单调时钟多走了多久
+    ///
+    /// Linux 上单调时钟在系统睡眠期间停止，两者的差值就是睡眠的时长；
+    /// 手动调整系统时间也会产生差值。
+    pub fn wall_drift_since(&self, earlier: &Now) -> Duration {
+        let wall = self.wall.signed_duration_since(earlier.wall).to_std().unwrap_or(Duration::ZERO);
+        wall.saturating_sub(self.instant.saturating_duration_since(earlier.instant))
+    }
+}
+
+/// 读取当前时间，测试中可以替换为手动推进的时钟
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Now;
+}
+
+/// 系统时钟
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Now {
+        Now::current()
+    }
+}
+
+/// 只在测试中手动推进的时钟
+#[cfg(test)]
+pub struct ManualClock(std::sync::Mutex<Now>);
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock(std::sync::Mutex::new(Now::current()))
+    }
+
+    /// 正常经过 `duration`
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.0.lock().unwrap();
+        *now = now.advanced(duration);
+    }
+
+    /// 系统睡眠 `duration` 后唤醒
+    pub fn sleep(&self, duration: Duration) {
+        let mut now = self.0.lock().unwrap();
+        *now = now.slept(duration);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Now {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// 从 `now` 起下一次到达本地时间 `time` 的时刻
+///
+/// 今天的这个时间已过时顺延到明天。夏令时回拨导致出现两次时取第一次，
+/// 拨快导致这个时间不存在时取跳变后的同一钟面时间。
+pub fn next_occurrence<Tz: TimeZone>(now: &DateTime<Tz>, time: NaiveTime) -> DateTime<Tz> {
+    let tz = now.timezone();
+    let mut date = now.date_naive();
+    loop {
+        let naive = date.and_time(time);
+        let candidate = match tz.from_local_datetime(&naive) {
+            LocalResult::Single(t) => Some(t),
+            LocalResult::Ambiguous(earliest, _) => Some(earliest),
+            LocalResult::None => tz.from_local_datetime(&(naive + TimeDelta::hours(1))).earliest(),
+        };
+        if let Some(candidate) = candidate
+            && candidate > *now
+        {
+            return candidate;
+        }
+        date = date.succ_opt().expect("日期超出范围");
+    }
+}
+
+/// 从 `now` 到下一次到达 `time` 的秒数
+pub fn seconds_until<Tz: TimeZone>(now: &DateTime<Tz>, time: NaiveTime) -> u64 {
+    let delta = next_occurrence(now, time).signed_duration_since(now.clone());
+    delta.num_seconds().max(0) as u64
+}
+
+/// 接下来的 `count` 个整点
+///
+/// 距离不足 30 分钟的整点跳过，避免选中后马上到期。
+pub fn upcoming_round_times<Tz: TimeZone>(now: &DateTime<Tz>, count: u32) -> Vec<NaiveTime> {
+    let skip = if now.minute() >= 30 { 2 } else { 1 };
+    (0..count)
+        .map(|i| NaiveTime::from_hms_opt((now.hour() + skip + i) % 24, 0, 0).expect("整点总是有效的"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, Utc};
+    use chrono_tz::Europe::Berlin;
+
+    fn hm(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn later_today() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 9, 15, 0).unwrap();
+        assert_eq!(seconds_until(&now, hm(18, 0)), (8 * 60 + 45) * 60);
+    }
+
+    #[test]
+    fn rolls_past_midnight() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 23, 0, 0).unwrap();
+        assert_eq!(seconds_until(&now, hm(1, 30)), 150 * 60);
+        // 恰好是当前时间时取明天
+        assert_eq!(seconds_until(&now, hm(23, 0)), 24 * 3600);
+    }
+
+    #[test]
+    fn spring_forward_skips_missing_hour() {
+        // 2024-03-31 02:00 柏林拨快到 03:00，当天只有 23 小时
+        let now = Berlin.with_ymd_and_hms(2024, 3, 31, 0, 0, 0).unwrap();
+        assert_eq!(seconds_until(&now, hm(6, 0)), 5 * 3600);
+        // 02:30 不存在，取跳变后的 03:30
+        let target = next_occurrence(&now, hm(2, 30));
+        assert_eq!(target.naive_local(), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap().and_time(hm(3, 30)));
+        assert_eq!(seconds_until(&now, hm(2, 30)), 2 * 3600 + 30 * 60);
+    }
+
+    #[test]
+    fn fall_back_uses_first_occurrence() {
+        // 2024-10-27 03:00 柏林回拨到 02:00，当天有 25 小时
+        let now = Berlin.with_ymd_and_hms(2024, 10, 27, 0, 0, 0).unwrap();
+        assert_eq!(seconds_until(&now, hm(6, 0)), 7 * 3600);
+        assert_eq!(seconds_until(&now, hm(2, 30)), 2 * 3600 + 30 * 60);
+    }
+
+    #[test]
+    fn drift_measures_sleep_only() {
+        let start = Now::current();
+        assert_eq!(start.advanced(Duration::from_secs(600)).wall_drift_since(&start), Duration::ZERO);
+        let resumed = start.advanced(Duration::from_secs(60)).slept(Duration::from_secs(3600));
+        assert_eq!(resumed.wall_drift_since(&start), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn round_times_wrap_and_skip_near_hours() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 21, 10, 0).unwrap();
+        assert_eq!(upcoming_round_times(&now, 3), vec![hm(22, 0), hm(23, 0), hm(0, 0)]);
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 21, 40, 0).unwrap();
+        assert_eq!(upcoming_round_times(&now, 2), vec![hm(23, 0), hm(0, 0)]);
+    }
+}
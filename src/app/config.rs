@@ -0,0 +1,123 @@
+//! 用户配置文件
+//!
+//! 配置保存在 Windows 的 `%APPDATA%\Keep Screen\config.json`，
+//! 或 Linux 的 `$XDG_CONFIG_HOME/keep-screen/config.json`。文件不存在时使用默认值。
+
+use super::power::{self, BackendKind};
+use super::rules::RuleConfig;
+use super::session::Startup;
+use super::state::{ExpiryAction, KeepMode, MissedExpiry};
+use chrono::NaiveTime;
+use serde::Deserialize;
+use std::io;
+use std::path::PathBuf;
+
+/// 配置文件不存在时写入的模板
+const TEMPLATE: &str = "{\n    \"durations\": []\n}\n";
+
+/// 配置文件的内容，所有字段都可以省略
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// 后端的尝试顺序，排在前面的优先
+    pub backends: Vec<BackendKind>,
+    /// 启动时的保持模式
+    pub mode: KeepMode,
+    /// 在内置选项之外添加的时长，单位为分钟
+    pub durations: Vec<u32>,
+    /// 在接下来的几个整点之外添加的“保持到”时间，例如 `"18:00"`
+    pub until: Vec<NaiveTime>,
+    /// 计时结束前多少分钟发出通知，为 0 时不通知
+    pub warn_before_minutes: u32,
+    /// 计时结束时默认执行的操作
+    pub expiry_action: ExpiryAction,
+    /// `expiry_action` 为 `command` 时运行的命令
+    pub expiry_command: Option<String>,
+    /// 计时在系统睡眠期间到期时如何处理
+    pub missed_expiry: MissedExpiry,
+    /// 启动时关闭、开启还是恢复上次的会话
+    pub startup: Startup,
+    /// 满足时自动保持唤醒的规则
+    pub rules: Vec<RuleConfig>,
+    /// 设置后每隔这么多秒模拟一次输入，用于忽略保持唤醒请求的机器
+    pub jiggle_interval_secs: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            backends: power::default_order().to_vec(),
+            mode: KeepMode::DisplayAndSystem,
+            durations: Vec::new(),
+            until: Vec::new(),
+            warn_before_minutes: 5,
+            expiry_action: ExpiryAction::Release,
+            expiry_command: None,
+            missed_expiry: MissedExpiry::Release,
+            startup: Startup::Restore,
+            rules: Vec::new(),
+            jiggle_interval_secs: None,
+        }
+    }
+}
+
+/// 配置文件所在的目录
+pub fn config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("Keep Screen"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|dir| dir.join("keep-screen"))
+    }
+}
+
+/// 配置文件的路径
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.json"))
+}
+
+/// 用系统默认程序打开配置文件，不存在时先写入模板
+///
+/// 自定义时长和“保持到”时间在下次弹出菜单时重新读取，其余设置需要重启程序才能生效。
+pub fn open_in_editor() -> io::Result<()> {
+    let path = config_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "找不到配置目录"))?;
+    if !path.exists() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, TEMPLATE)?;
+    }
+
+    #[cfg(windows)]
+    return super::win_api::shell_open(&path);
+
+    #[cfg(not(windows))]
+    return std::process::Command::new("xdg-open").arg(&path).spawn().map(drop);
+}
+
+/// 加载配置，出错时使用默认值
+pub fn load() -> Config {
+    reload().unwrap_or_default()
+}
+
+/// 重新读取配置，文件不存在时使用默认值
+///
+/// 无法解析时返回 `None`，调用方应当保留之前的设置，以免编辑到一半的文件清空已有选项。
+pub fn reload() -> Option<Config> {
+    let Some(path) = config_path() else {
+        return Some(Config::default());
+    };
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return Some(Config::default()),
+    };
+    match serde_json::from_str(&content) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            eprintln!("解析配置文件 {} 失败: {}", path.display(), e);
+            None
+        }
+    }
+}
@@ -0,0 +1,485 @@
+//! 执行事件产生的副作用，与托盘 UI 和具体平台 API 解耦
+
+use super::clock::{Clock, SystemClock};
+use super::config;
+use super::expiry;
+use super::notify::Notifier;
+use super::power::{PowerBackend, REASON};
+use super::reducer::{reduce, Effect};
+use super::session::{Session, SessionStore};
+use super::state::{AppState, Event, KeepMode, Warning};
+use super::timer::Timer;
+use super::wait;
+use crossbeam_channel::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// 定期确认保持唤醒仍然生效的间隔
+const VERIFY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 处理完一个事件后事件循环应当做什么
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Flow {
+    /// 继续运行，`refresh_menu` 表示是否需要重建菜单
+    Continue { refresh_menu: bool },
+    /// 弹出托盘菜单
+    ShowMenu,
+    /// 已释放保持唤醒，应当退出程序
+    Exit,
+}
+
+/// 持有后端、计时器和通知，负责执行 [`reduce`] 产生的副作用
+pub struct Controller {
+    backend: Box<dyn PowerBackend>,
+    notifier: Box<dyn Notifier>,
+    clock: Arc<dyn Clock>,
+    timer: Timer,
+    event_tx: Sender<Event>,
+    session_store: Option<SessionStore>,
+}
+
+impl Controller {
+    /// `event_tx` 用于计时结束时发送事件
+    pub fn new(backend: Box<dyn PowerBackend>, notifier: Box<dyn Notifier>, event_tx: Sender<Event>) -> Self {
+        Self::with_clock(backend, notifier, event_tx, Arc::new(SystemClock))
+    }
+
+    /// 使用指定的时钟，便于在测试中模拟时间流逝和系统睡眠
+    pub fn with_clock(
+        backend: Box<dyn PowerBackend>,
+        notifier: Box<dyn Notifier>,
+        event_tx: Sender<Event>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Controller {
+            backend,
+            notifier,
+            timer: Timer::new(Arc::clone(&clock)),
+            clock,
+            event_tx,
+            session_store: None,
+        }
+    }
+
+    /// 每次处理事件后把会话保存到 `store`，以便重启后恢复
+    pub fn set_session_store(&mut self, store: SessionStore) {
+        self.session_store = Some(store);
+    }
+
+    /// 实际使用的后端名称，显示在托盘菜单中
+    #[cfg(windows)]
+    pub fn backend_name(&self) -> String {
+        self.backend.name()
+    }
+
+    /// 按当前状态申请或释放保持唤醒
+    pub fn apply_keep_awake(&mut self, state: &mut AppState) {
+        let hold = state.holds_keep_awake();
+        if !hold {
+            state.warning = None;
+        }
+        // 后端已处于目标状态时不重复调用
+        if hold == self.backend.query().is_some() {
+            return;
+        }
+        if hold {
+            self.acquire(state, state.mode);
+        } else {
+            self.release();
+        }
+    }
+
+    /// 为恢复的会话重新启动计时器，截止时间已过时按到期处理
+    pub fn restore_timer(&mut self, state: &mut AppState) {
+        if state.deadline.is_some() {
+            self.handle_event(state, Event::Resumed);
+        }
+    }
+
+    /// 处理单个事件：先计算新状态，再依次执行副作用
+    pub fn handle_event(&mut self, state: &mut AppState, event: Event) -> Flow {
+        let (next, effects) = reduce(state, event, self.clock.now());
+        *state = next;
+
+        let mut flow = Flow::Continue { refresh_menu: false };
+        for effect in effects {
+            match effect {
+                Effect::Acquire(mode) => self.acquire(state, mode),
+                Effect::Release => self.release(),
+                Effect::StartTimer { deadline, generation } => {
+                    self.timer.start(deadline, generation, self.event_tx.clone())
+                }
+                Effect::StopTimer => self.timer.stop(),
+                Effect::CloseNotification => self.notifier.close(),
+                Effect::RunExpiryAction(action) => {
+                    if let Err(e) = expiry::run(action, state.expiry_command.as_deref()) {
+                        eprintln!("执行计时结束操作失败: {}", e);
+                    }
+                }
+                Effect::WatchProcess(pid) => wait::watch(pid, self.event_tx.clone()),
+                Effect::Verify => {
+                    if self.verify(state) {
+                        flow = Flow::Continue { refresh_menu: true };
+                    }
+                }
+                Effect::NotifyExpiry(minutes) => {
+                    if let Err(e) = self.notifier.notify_expiry(minutes, &state.translations) {
+                        eprintln!("显示计时结束通知失败: {}", e);
+                    }
+                }
+                Effect::RefreshMenu => flow = Flow::Continue { refresh_menu: true },
+                Effect::ShowMenu => flow = Flow::ShowMenu,
+                Effect::OpenConfig => {
+                    if let Err(e) = config::open_in_editor() {
+                        eprintln!("打开配置文件失败: {}", e);
+                    }
+                }
+                Effect::Exit => flow = Flow::Exit,
+            }
+        }
+        // 退出时计时器已经停止，保留退出前的会话，下次启动时才能恢复
+        if flow != Flow::Exit
+            && let Some(store) = &mut self.session_store
+            && let Err(e) = store.save(&Session::of(state))
+        {
+            eprintln!("保存会话失败: {}", e);
+        }
+        flow
+    }
+
+    /// 按指定模式申请保持唤醒，已持有时替换之前的申请
+    ///
+    /// 申请失败或读回系统状态时找不到申请，都会记录到 `state.warning`。
+    fn acquire(&mut self, state: &mut AppState, mode: KeepMode) {
+        let backend = self.backend.as_mut();
+        state.warning = match backend.acquire(mode, REASON) {
+            Err(e) => {
+                eprintln!("{} 后端申请保持唤醒失败: {}", backend.name(), e);
+                Some(Warning::AcquireFailed(e.to_string()))
+            }
+            Ok(()) if backend.verify() == Some(false) => {
+                eprintln!("{} 后端申请成功，但系统中找不到该申请", backend.name());
+                Some(Warning::NotConfirmed)
+            }
+            Ok(()) => None,
+        };
+    }
+
+    /// 读回后端状态并更新 `state.warning`，返回警告是否变化
+    ///
+    /// 申请失败的警告保留到下次申请，其余情况按读回的结果设置或清除警告。
+    fn verify(&self, state: &mut AppState) -> bool {
+        if matches!(state.warning, Some(Warning::AcquireFailed(_))) {
+            return false;
+        }
+        let warning = match self.backend.verify() {
+            Some(false) => Some(Warning::NotConfirmed),
+            _ => None,
+        };
+        if warning == state.warning {
+            return false;
+        }
+        if warning.is_some() {
+            eprintln!("{} 后端的申请已不再生效", self.backend.name());
+        }
+        state.warning = warning;
+        true
+    }
+
+    fn release(&mut self) {
+        if let Err(e) = self.backend.release() {
+            eprintln!("{} 后端释放保持唤醒失败: {}", self.backend.name(), e);
+        }
+    }
+}
+
+/// 在后台线程中定期发送 [`Event::Verify`]，事件循环结束后随之退出
+pub fn watch_backend(event_tx: Sender<Event>) {
+    thread::spawn(move || loop {
+        thread::sleep(VERIFY_INTERVAL);
+        if event_tx.send(Event::Verify).is_err() {
+            return;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::i18n::Translations;
+    use crate::app::power::recording::{Call, RecordingBackend};
+    use crate::app::clock::ManualClock;
+    use crate::app::state::{DurationOption, ExpiryAction, MissedExpiry};
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    /// 记录通知的显示和关闭
+    #[derive(Clone, Default)]
+    struct RecordingNotifier(Rc<RefCell<Vec<String>>>);
+
+    impl Notifier for RecordingNotifier {
+        fn notify_expiry(&mut self, remaining_minutes: u32, _t: &Translations) -> io::Result<()> {
+            self.0.borrow_mut().push(format!("notify {}", remaining_minutes));
+            Ok(())
+        }
+
+        fn close(&mut self) {
+            self.0.borrow_mut().push("close".to_string());
+        }
+    }
+
+    fn setup() -> (AppState, RecordingBackend, Controller) {
+        with_backend(RecordingBackend::new())
+    }
+
+    fn with_backend(backend: RecordingBackend) -> (AppState, RecordingBackend, Controller) {
+        let (event_tx, _event_rx) = crossbeam_channel::unbounded();
+        let controller = Controller::new(Box::new(backend.clone()), Box::new(RecordingNotifier::default()), event_tx);
+        (AppState::new(), backend, controller)
+    }
+
+    #[test]
+    fn toggle_releases_then_reacquires() {
+        let (mut state, backend, mut controller) = setup();
+        controller.apply_keep_awake(&mut state);
+
+        controller.handle_event(&mut state, Event::ToggleActive);
+        assert!(!state.is_active);
+        assert_eq!(controller.backend.query(), None);
+
+        controller.handle_event(&mut state, Event::ToggleActive);
+        assert!(state.is_active);
+        assert_eq!(controller.backend.query(), Some(REASON));
+        assert_eq!(
+            backend.calls(),
+            vec![
+                Call::Acquire(KeepMode::DisplayAndSystem, REASON.to_string()),
+                Call::Release,
+                Call::Acquire(KeepMode::DisplayAndSystem, REASON.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn exit_releases_and_stops_timer() {
+        let (mut state, backend, mut controller) = setup();
+        controller.apply_keep_awake(&mut state);
+        controller.handle_event(&mut state, Event::SetDuration(DurationOption::Minutes(15)));
+        assert!(controller.timer.is_running());
+
+        let flow = controller.handle_event(&mut state, Event::Exit);
+        assert_eq!(flow, Flow::Exit);
+        assert!(!controller.timer.is_running());
+        assert_eq!(backend.calls().last(), Some(&Call::Release));
+        assert_eq!(controller.backend.query(), None);
+    }
+
+    #[test]
+    fn set_duration_only_starts_timer_while_active() {
+        let (mut state, _, mut controller) = setup();
+        controller.handle_event(&mut state, Event::ToggleActive);
+        controller.handle_event(&mut state, Event::SetDuration(DurationOption::Minutes(30)));
+        assert_eq!(state.duration, DurationOption::Minutes(30));
+        assert!(!controller.timer.is_running());
+
+        controller.handle_event(&mut state, Event::ToggleActive);
+        assert!(controller.timer.is_running());
+    }
+
+    #[test]
+    fn set_mode_reacquires_only_while_active() {
+        let (mut state, backend, mut controller) = setup();
+        controller.apply_keep_awake(&mut state);
+        controller.handle_event(&mut state, Event::SetMode(KeepMode::SystemOnly));
+        assert_eq!(
+            backend.calls().last(),
+            Some(&Call::Acquire(KeepMode::SystemOnly, REASON.to_string()))
+        );
+
+        controller.handle_event(&mut state, Event::ToggleActive);
+        controller.handle_event(&mut state, Event::SetMode(KeepMode::DisplayOnly));
+        assert_eq!(state.mode, KeepMode::DisplayOnly);
+        assert_eq!(backend.calls().last(), Some(&Call::Release));
+    }
+
+    #[test]
+    fn noop_does_not_touch_backend_or_menu() {
+        let (mut state, backend, mut controller) = setup();
+        let flow = controller.handle_event(&mut state, Event::NoOp);
+        assert_eq!(flow, Flow::Continue { refresh_menu: false });
+        assert!(backend.calls().is_empty());
+    }
+
+    #[test]
+    fn show_menu_is_forwarded() {
+        let (mut state, backend, mut controller) = setup();
+        let flow = controller.handle_event(&mut state, Event::ShowMenu);
+        assert_eq!(flow, Flow::ShowMenu);
+        assert!(backend.calls().is_empty());
+    }
+
+    #[test]
+    fn failed_acquire_keeps_running() {
+        let (mut state, _, mut controller) = with_backend(RecordingBackend::failing());
+        controller.apply_keep_awake(&mut state);
+        assert_eq!(controller.backend.query(), None);
+        assert!(matches!(state.warning, Some(Warning::AcquireFailed(_))));
+
+        let flow = controller.handle_event(&mut state, Event::ThemeChanged);
+        assert_eq!(flow, Flow::Continue { refresh_menu: true });
+
+        controller.handle_event(&mut state, Event::ToggleActive);
+        assert_eq!(state.warning, None);
+    }
+
+    #[test]
+    fn unverified_acquire_sets_warning() {
+        let (mut state, _, mut controller) = with_backend(RecordingBackend::unverified());
+        controller.apply_keep_awake(&mut state);
+        assert_eq!(state.warning, Some(Warning::NotConfirmed));
+
+        controller.handle_event(&mut state, Event::ToggleActive);
+        assert_eq!(state.warning, None);
+    }
+
+    #[test]
+    fn periodic_verify_updates_warning() {
+        let (mut state, _, mut controller) = setup();
+        controller.apply_keep_awake(&mut state);
+        assert_eq!(controller.handle_event(&mut state, Event::Verify), Flow::Continue { refresh_menu: false });
+
+        // 申请在之后失效，例如屏保服务重启
+        controller.backend = Box::new(RecordingBackend::unverified());
+        controller.backend.acquire(KeepMode::DisplayAndSystem, REASON).unwrap();
+        assert_eq!(controller.handle_event(&mut state, Event::Verify), Flow::Continue { refresh_menu: true });
+        assert_eq!(state.warning, Some(Warning::NotConfirmed));
+
+        // 重新生效后清除警告
+        controller.backend = Box::new(RecordingBackend::new());
+        controller.backend.acquire(KeepMode::DisplayAndSystem, REASON).unwrap();
+        assert_eq!(controller.handle_event(&mut state, Event::Verify), Flow::Continue { refresh_menu: true });
+        assert_eq!(state.warning, None);
+    }
+
+    #[test]
+    fn verified_acquire_has_no_warning() {
+        let (mut state, _, mut controller) = setup();
+        controller.apply_keep_awake(&mut state);
+        assert_eq!(controller.backend.verify(), Some(true));
+        assert_eq!(state.warning, None);
+    }
+
+    #[test]
+    fn notification_is_shown_then_closed_when_timer_changes() {
+        let (event_tx, _event_rx) = crossbeam_channel::unbounded();
+        let notifier = RecordingNotifier::default();
+        let mut controller = Controller::new(Box::new(RecordingBackend::new()), Box::new(notifier.clone()), event_tx);
+        let mut state = AppState {
+            deadline: Some(chrono::Utc::now() + chrono::TimeDelta::minutes(3)),
+            ..AppState::new()
+        };
+
+        controller.handle_event(&mut state, Event::Tick);
+        assert!(state.expiry_warned);
+        controller.handle_event(&mut state, Event::Extend(15));
+        assert_eq!(*notifier.0.borrow(), ["notify 3", "close"]);
+    }
+
+    #[test]
+    fn notification_stays_open_when_resume_keeps_deadline() {
+        let (event_tx, _event_rx) = crossbeam_channel::unbounded();
+        let notifier = RecordingNotifier::default();
+        let mut controller = Controller::new(Box::new(RecordingBackend::new()), Box::new(notifier.clone()), event_tx);
+        let mut state = AppState {
+            deadline: Some(chrono::Utc::now() + chrono::TimeDelta::minutes(3)),
+            ..AppState::new()
+        };
+
+        controller.handle_event(&mut state, Event::Tick);
+        controller.handle_event(&mut state, Event::Resumed);
+        assert!(state.expiry_warned);
+        assert_eq!(*notifier.0.borrow(), ["notify 3"]);
+
+        // 之后结束计时仍会关闭通知
+        controller.handle_event(&mut state, Event::ToggleActive);
+        assert_eq!(*notifier.0.borrow(), ["notify 3", "close"]);
+    }
+
+    #[test]
+    fn deadline_passed_during_sleep_follows_policy() {
+        let clock = Arc::new(ManualClock::new());
+        let (event_tx, event_rx) = crossbeam_channel::unbounded();
+        let backend = RecordingBackend::new();
+        let mut controller = Controller::with_clock(
+            Box::new(backend.clone()),
+            Box::new(RecordingNotifier::default()),
+            event_tx,
+            clock.clone(),
+        );
+        let mut state = AppState {
+            expiry_action: ExpiryAction::Command,
+            missed_expiry: MissedExpiry::Extend,
+            ..AppState::new()
+        };
+        controller.apply_keep_awake(&mut state);
+        controller.handle_event(&mut state, Event::SetDuration(DurationOption::Minutes(30)));
+
+        // 截止时间在睡眠期间过去，唤醒后继续保持 15 分钟，而不是运行命令
+        clock.sleep(Duration::from_secs(2 * 3600));
+        controller.handle_event(&mut state, Event::Resumed);
+        assert!(state.is_active);
+        assert_eq!(state.remaining(&clock.now().wall), Some(Duration::from_secs(15 * 60)));
+        assert_eq!(backend.calls().last(), Some(&Call::Acquire(KeepMode::DisplayAndSystem, REASON.to_string())));
+
+        // 旧计时器线程随后发出的事件已经过时
+        while let Ok(event) = event_rx.recv_timeout(Duration::from_millis(100)) {
+            controller.handle_event(&mut state, event);
+        }
+        assert!(state.is_active);
+        controller.handle_event(&mut state, Event::Exit);
+    }
+
+    #[test]
+    fn pause_releases_backend_until_resumed() {
+        let (mut state, backend, mut controller) = setup();
+        controller.apply_keep_awake(&mut state);
+        controller.handle_event(&mut state, Event::SetDuration(DurationOption::Minutes(30)));
+
+        controller.handle_event(&mut state, Event::TogglePause);
+        assert_eq!(controller.backend.query(), None);
+        assert!(!controller.timer.is_running());
+        // 重新应用状态时不会在暂停期间申请
+        controller.apply_keep_awake(&mut state);
+        assert_eq!(controller.backend.query(), None);
+
+        controller.handle_event(&mut state, Event::TogglePause);
+        assert_eq!(controller.backend.query(), Some(REASON));
+        assert!(controller.timer.is_running());
+        assert_eq!(backend.calls().iter().filter(|call| **call == Call::Release).count(), 1);
+    }
+
+    #[test]
+    fn session_is_saved_but_kept_on_exit() {
+        let path = std::env::temp_dir().join(format!("keep-screen-controller-{}.json", std::process::id()));
+        let (mut state, _, mut controller) = setup();
+        controller.set_session_store(SessionStore::new(path.clone()));
+        controller.handle_event(&mut state, Event::SetDuration(DurationOption::Minutes(30)));
+        let saved = SessionStore::new(path.clone()).load().unwrap();
+        assert_eq!(saved.duration, DurationOption::Minutes(30));
+        assert_eq!(saved.deadline, state.deadline);
+
+        controller.handle_event(&mut state, Event::Exit);
+        assert_eq!(SessionStore::new(path.clone()).load(), Some(saved.clone()));
+        let _ = std::fs::remove_file(&path);
+
+        // 重启后恢复的截止时间重新启动计时器
+        let (mut state, _, mut controller) = setup();
+        state.deadline = saved.deadline;
+        controller.restore_timer(&mut state);
+        assert!(controller.timer.is_running());
+        assert_eq!(state.deadline, saved.deadline);
+    }
+}
@@ -0,0 +1,153 @@
+//! 计时结束时执行的操作
+//!
+//! Linux 上锁屏和睡眠通过 systemd-logind 的 `LockSession`/`Suspend`，关闭显示器通过 X11 DPMS；
+//! Windows 上分别使用 `LockWorkStation`、`SC_MONITORPOWER` 和 `SetSuspendState`。
+
+use super::state::ExpiryAction;
+use std::io;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// 执行计时结束的操作，`command` 是 [`ExpiryAction::Command`] 要运行的命令
+pub fn run(action: ExpiryAction, command: Option<&str>) -> io::Result<()> {
+    match action {
+        ExpiryAction::Release => Ok(()),
+        ExpiryAction::Command => {
+            let command = command.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "配置中没有设置命令"))?;
+            spawn_command(command)
+        }
+        #[cfg(target_os = "linux")]
+        ExpiryAction::Lock => LogindSession::system()?.lock(),
+        #[cfg(target_os = "linux")]
+        ExpiryAction::Suspend => LogindSession::system()?.suspend(),
+        #[cfg(target_os = "linux")]
+        ExpiryAction::DisplayOff => x11_display_off(),
+        #[cfg(windows)]
+        ExpiryAction::Lock => super::win_api::lock_workstation(),
+        #[cfg(windows)]
+        ExpiryAction::Suspend => super::win_api::suspend(),
+        #[cfg(windows)]
+        ExpiryAction::DisplayOff => super::win_api::turn_off_display(),
+        #[cfg(not(any(windows, target_os = "linux")))]
+        _ => Err(io::Error::new(io::ErrorKind::Unsupported, "当前平台不支持该操作")),
+    }
+}
+
+/// 通过系统 shell 在后台运行命令，不等待它结束
+fn spawn_command(command: &str) -> io::Result<()> {
+    #[cfg(windows)]
+    let mut child = Command::new("cmd").args(["/C", command]).stdin(Stdio::null()).spawn()?;
+    #[cfg(not(windows))]
+    let mut child = Command::new("sh").args(["-c", command]).stdin(Stdio::null()).spawn()?;
+    // 在后台回收子进程，避免留下僵尸进程
+    thread::spawn(move || {
+        if let Ok(status) = child.wait()
+            && !status.success()
+        {
+            eprintln!("计时结束命令退出状态: {}", status);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1",
+    gen_async = false,
+    blocking_name = "ManagerProxy"
+)]
+trait Manager {
+    fn lock_session(&self, session_id: &str) -> zbus::Result<()>;
+
+    fn suspend(&self, interactive: bool) -> zbus::Result<()>;
+}
+
+/// 通过 logind 操作当前会话
+#[cfg(target_os = "linux")]
+struct LogindSession {
+    connection: zbus::blocking::Connection,
+}
+
+#[cfg(target_os = "linux")]
+impl LogindSession {
+    fn system() -> io::Result<Self> {
+        let connection = zbus::blocking::Connection::system().map_err(io::Error::other)?;
+        Ok(LogindSession { connection })
+    }
+
+    fn manager(&self) -> io::Result<ManagerProxy<'_>> {
+        ManagerProxy::new(&self.connection).map_err(io::Error::other)
+    }
+
+    /// 锁定当前会话，不在登录会话中运行时由 logind 按调用者推断
+    fn lock(&self) -> io::Result<()> {
+        let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+        self.manager()?.lock_session(&session).map_err(io::Error::other)
+    }
+
+    fn suspend(&self) -> io::Result<()> {
+        self.manager()?.suspend(false).map_err(io::Error::other)
+    }
+}
+
+/// 通过 DPMS 立即关闭 X11 显示器
+#[cfg(target_os = "linux")]
+fn x11_display_off() -> io::Result<()> {
+    use x11rb::connection::Connection as _;
+    use x11rb::protocol::dpms::{ConnectionExt as _, DPMSMode};
+
+    let (connection, _) = x11rb::connect(None).map_err(io::Error::other)?;
+    connection
+        .dpms_force_level(DPMSMode::OFF)
+        .map_err(io::Error::other)?
+        .check()
+        .map_err(io::Error::other)?;
+    connection.flush().map_err(io::Error::other)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::app::testbus::{PrivateBus, StubLogin1};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn lock_and_suspend_call_logind() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let stub = StubLogin1::default();
+        let _service = stub.serve(&bus);
+
+        let session = LogindSession {
+            connection: bus.connect(),
+        };
+        session.lock().unwrap();
+        session.suspend().unwrap();
+        let calls = stub.calls.calls();
+        assert!(calls[0].starts_with("LockSession("), "{:?}", calls);
+        assert_eq!(calls[1], "Suspend(false)");
+    }
+
+    #[test]
+    fn command_runs_in_shell() {
+        let marker = std::env::temp_dir().join(format!("keep-screen-expiry-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        run(ExpiryAction::Command, Some(&format!("echo done > '{}'", marker.display()))).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !marker.exists() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(marker.exists());
+        let _ = std::fs::remove_file(&marker);
+    }
+
+    #[test]
+    fn command_requires_configuration() {
+        assert!(run(ExpiryAction::Command, None).is_err());
+        assert!(run(ExpiryAction::Release, None).is_ok());
+    }
+}
@@ -0,0 +1,72 @@
+//! 无托盘图标的事件循环，用于 Linux 桌面
+//!
+//! 没有菜单可用，因此通过信号控制：SIGUSR1 切换保持亮屏，SIGUSR2 将计时延长 15 分钟，
+//! SIGINT/SIGTERM 退出。
+
+use super::controller::{self, Controller, Flow};
+use super::notify;
+use super::power::PowerBackend;
+use super::rules::{self, Rule};
+use super::session::SessionStore;
+use super::state::{AppState, Event};
+use crossbeam_channel::Sender;
+use signal_hook::consts::{SIGINT, SIGTERM, SIGUSR1, SIGUSR2};
+use signal_hook::iterator::Signals;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// 运行事件循环，直到收到退出事件
+pub fn run_headless_event_loop(
+    app_state: Arc<Mutex<AppState>>,
+    backend: Box<dyn PowerBackend>,
+    session_store: Option<SessionStore>,
+    rules: Vec<Box<dyn Rule>>,
+) {
+    let (event_tx, event_rx) = crossbeam_channel::unbounded();
+    forward_signals(event_tx.clone());
+    rules::watch(rules, event_tx.clone());
+    controller::watch_backend(event_tx.clone());
+    let mut controller = Controller::new(backend, notify::create(event_tx.clone()), event_tx);
+    if let Some(store) = session_store {
+        controller.set_session_store(store);
+    }
+
+    if let Ok(mut state) = app_state.lock() {
+        controller.apply_keep_awake(&mut state);
+        controller.restore_timer(&mut state);
+    }
+
+    for event in event_rx.iter() {
+        let mut state = match app_state.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                eprintln!("获取应用状态锁失败: {}", e);
+                continue;
+            }
+        };
+        if controller.handle_event(&mut state, event) == Flow::Exit {
+            break;
+        }
+    }
+}
+
+/// 把进程信号转换为事件
+fn forward_signals(event_tx: Sender<Event>) {
+    let mut signals = match Signals::new([SIGINT, SIGTERM, SIGUSR1, SIGUSR2]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            eprintln!("注册信号处理失败: {}", e);
+            return;
+        }
+    };
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            let event = match signal {
+                SIGUSR1 => Event::ToggleActive,
+                SIGUSR2 => Event::Extend(15),
+                _ => Event::Exit,
+            };
+            let _ = event_tx.send(event);
+        }
+    });
+}
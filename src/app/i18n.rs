@@ -6,9 +6,17 @@ use std::collections::HashMap;
 pub struct Translations {
     #[serde(flatten)]
     map: HashMap<String, String>,
+    /// 系统区域设置中的时间格式，优先于翻译文件中的 `time_format`
+    #[serde(skip)]
+    system_time_format: Option<String>,
 }
 
 impl Translations {
+    /// 显示时间点使用的 chrono 格式
+    pub fn time_format(&self) -> String {
+        self.system_time_format.clone().unwrap_or_else(|| self.get("time_format"))
+    }
+
     pub fn get(&self, key: &str) -> String {
         self.map.get(key).cloned().unwrap_or_else(|| {
             eprintln!("翻译键未找到: {}", key);
@@ -26,12 +34,92 @@ pub fn load() -> Translations {
     } else {
         include_str!("../../res/locales/en.json")
     };
+    let mut translations = parse(content);
+    translations.system_time_format = system_time_format();
+    translations
+}
+
+/// 读取 `LC_TIME` 的时间格式并去掉秒，没有设置区域时返回 `None`
+///
+/// 通过 `newlocale` 创建独立的区域对象读取，不修改进程全局的区域设置，
+/// 可以在其他线程已经运行时调用。
+#[cfg(target_os = "linux")]
+fn system_time_format() -> Option<String> {
+    use std::ffi::CStr;
+    // 与 setlocale(LC_TIME, "") 相同的查找顺序
+    let name = ["LC_ALL", "LC_TIME", "LANG"]
+        .into_iter()
+        .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))?;
+    if matches!(name.as_str(), "C" | "POSIX") {
+        return None;
+    }
+    // SAFETY: 区域对象只在本函数内使用，读取完格式字符串后才释放
+    let format = unsafe {
+        let locale = libc::newlocale(libc::LC_TIME_MASK, c"".as_ptr(), std::ptr::null_mut());
+        if locale.is_null() {
+            return None;
+        }
+        let format = CStr::from_ptr(libc::nl_langinfo_l(libc::T_FMT, locale)).to_string_lossy().into_owned();
+        libc::freelocale(locale);
+        format
+    };
+    short_time_format(&format)
+}
 
+/// Windows 上每次显示时通过 `GetTimeFormatEx` 按系统格式显示，不需要转换格式
+#[cfg(not(target_os = "linux"))]
+fn system_time_format() -> Option<String> {
+    None
+}
+
+/// 把 `nl_langinfo(T_FMT)` 的格式转换为不含秒的短格式
+///
+/// 秒前面的 `:` 或 `.` 一并去掉；没有分隔符时去掉秒后面的单位，例如 `%S秒`。
+#[cfg(any(target_os = "linux", test))]
+fn short_time_format(format: &str) -> Option<String> {
+    let mut format = format.replace("%T", "%H:%M:%S").replace("%r", "%I:%M:%S %p");
+    if let Some(index) = format.find("%S") {
+        let (before, after) = (&format[..index], &format[index + 2..]);
+        let short = match before.strip_suffix([':', '.']) {
+            Some(before) => format!("{}{}", before, after),
+            None => {
+                let unit = after.find(|c: char| c == '%' || c.is_whitespace()).unwrap_or(after.len());
+                format!("{}{}", before, &after[unit..])
+            }
+        };
+        format = short;
+    }
+    let format = format.trim();
+    format.contains('%').then(|| format.to_string())
+}
+
+/// 固定使用英文翻译，测试结果不受系统语言影响
+#[cfg(test)]
+pub fn english() -> Translations {
+    parse(include_str!("../../res/locales/en.json"))
+}
+
+fn parse(content: &str) -> Translations {
     serde_json::from_str(content)
         .unwrap_or_else(|e| {
             eprintln!("解析翻译文件失败: {}", e);
             Translations {
                 map: std::collections::HashMap::new(),
+                system_time_format: None,
             }
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_time_format_drops_seconds() {
+        assert_eq!(short_time_format("%H:%M:%S").as_deref(), Some("%H:%M"));
+        assert_eq!(short_time_format("%r").as_deref(), Some("%I:%M %p"));
+        assert_eq!(short_time_format("%T").as_deref(), Some("%H:%M"));
+        assert_eq!(short_time_format("%H時%M分%S秒").as_deref(), Some("%H時%M分"));
+        assert_eq!(short_time_format("").as_deref(), None);
+    }
+}
@@ -0,0 +1,71 @@
+//! 计时即将结束时的桌面通知
+//!
+//! Linux 上通过 `org.freedesktop.Notifications`，Windows 上通过托盘气泡提示。
+//! 用户选择延长时，通知向事件循环发送 [`EXTEND_EVENT`]。
+
+#[cfg(windows)]
+mod balloon;
+#[cfg(target_os = "linux")]
+mod freedesktop;
+
+use super::i18n::Translations;
+use super::state::{plural, Event, EXTEND_OPTIONS};
+use crossbeam_channel::Sender;
+use std::io;
+
+/// 通知中“延长”操作发送的事件
+pub const EXTEND_EVENT: Event = Event::Extend(EXTEND_OPTIONS[0]);
+
+/// 显示计时即将结束的通知
+pub trait Notifier {
+    /// 显示通知，`remaining_minutes` 是剩余的分钟数；已有通知时替换它
+    fn notify_expiry(&mut self, remaining_minutes: u32, t: &Translations) -> io::Result<()>;
+
+    /// 关闭仍在显示的通知
+    fn close(&mut self);
+}
+
+/// 没有可用的通知服务时使用，什么也不做
+pub struct NullNotifier;
+
+impl Notifier for NullNotifier {
+    fn notify_expiry(&mut self, _remaining_minutes: u32, _t: &Translations) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn close(&mut self) {}
+}
+
+/// 正文句子中的分钟数，不同于菜单中首字母大写的写法，例如 `5 minutes`
+fn minutes_in_sentence(minutes: u32, t: &Translations) -> String {
+    plural(t, "sentence_minutes", minutes)
+}
+
+/// 通知的正文
+fn expiry_body(remaining_minutes: u32, t: &Translations) -> String {
+    t.get("expiry_warning").replace("{}", &minutes_in_sentence(remaining_minutes, t))
+}
+
+/// 创建当前平台的通知方式，不可用时退回到不显示通知
+pub fn create(event_tx: Sender<Event>) -> Box<dyn Notifier> {
+    #[cfg(windows)]
+    return {
+        let _ = event_tx;
+        Box::new(balloon::BalloonNotifier::default())
+    };
+
+    #[cfg(target_os = "linux")]
+    return match freedesktop::FreedesktopNotifier::session(event_tx) {
+        Ok(notifier) => Box::new(notifier),
+        Err(e) => {
+            eprintln!("通知服务不可用，计时结束前不会提醒: {}", e);
+            Box::new(NullNotifier)
+        }
+    };
+
+    #[allow(unreachable_code)]
+    {
+        let _ = event_tx;
+        Box::new(NullNotifier)
+    }
+}
@@ -0,0 +1,32 @@
+//! 通过托盘气泡提示通知
+
+use super::{expiry_body, minutes_in_sentence, Notifier};
+use crate::app::i18n::Translations;
+use crate::app::state::EXTEND_OPTIONS;
+use crate::app::win_api;
+use std::io;
+
+/// 气泡提示没有按钮：点击气泡即延长，忽略或关闭则让计时结束
+#[derive(Default)]
+pub struct BalloonNotifier {
+    shown: bool,
+}
+
+impl Notifier for BalloonNotifier {
+    fn notify_expiry(&mut self, remaining_minutes: u32, t: &Translations) -> io::Result<()> {
+        let body = format!(
+            "{}\n{}",
+            expiry_body(remaining_minutes, t),
+            t.get("click_to_extend").replace("{}", &minutes_in_sentence(EXTEND_OPTIONS[0], t))
+        );
+        win_api::show_balloon("Keep Screen", &body)?;
+        self.shown = true;
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        if std::mem::take(&mut self.shown) {
+            win_api::hide_balloon();
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! 基于 `org.freedesktop.Notifications` 的通知
+
+use super::{expiry_body, Notifier, EXTEND_EVENT};
+use crate::app::i18n::Translations;
+use crate::app::state::{DurationOption, Event, EXTEND_OPTIONS};
+use crossbeam_channel::Sender;
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread;
+use zbus::blocking::Connection;
+use zbus::zvariant::Value;
+
+/// 通知中操作的标识
+const ACTION_EXTEND: &str = "extend";
+const ACTION_LET_END: &str = "let_end";
+
+/// “延长”按钮的文本，与菜单中的延长项一致
+fn extend_label(t: &Translations) -> String {
+    format!("+{}", DurationOption::Minutes(EXTEND_OPTIONS[0]).display_text(t))
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications",
+    gen_async = false,
+    blocking_name = "NotificationsProxy"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+}
+
+/// 通过桌面通知服务提醒，带有“延长”和“让它结束”两个按钮
+pub struct FreedesktopNotifier {
+    proxy: NotificationsProxy<'static>,
+    /// 当前通知的 id，0 表示没有
+    current: Arc<AtomicU32>,
+}
+
+impl FreedesktopNotifier {
+    /// 连接会话总线
+    pub fn session(event_tx: Sender<Event>) -> io::Result<Self> {
+        let connection = Connection::session().map_err(io::Error::other)?;
+        Self::with_connection(connection, event_tx)
+    }
+
+    /// 使用指定的总线连接，便于在测试中连接私有总线
+    pub fn with_connection(connection: Connection, event_tx: Sender<Event>) -> io::Result<Self> {
+        let proxy = NotificationsProxy::new(&connection).map_err(io::Error::other)?;
+        let current = Arc::new(AtomicU32::new(0));
+        // 先订阅信号再返回，避免错过显示通知后马上到来的操作
+        let actions = proxy.receive_action_invoked().map_err(io::Error::other)?;
+        let watched = Arc::clone(&current);
+        thread::spawn(move || {
+            for signal in actions {
+                let Ok(args) = signal.args() else {
+                    continue;
+                };
+                // 只处理我们自己的通知
+                let id = watched.load(Ordering::SeqCst);
+                if id == 0 || args.id != id {
+                    continue;
+                }
+                watched.store(0, Ordering::SeqCst);
+                if args.action_key == ACTION_EXTEND && event_tx.send(EXTEND_EVENT).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(FreedesktopNotifier { proxy, current })
+    }
+}
+
+impl Notifier for FreedesktopNotifier {
+    fn notify_expiry(&mut self, remaining_minutes: u32, t: &Translations) -> io::Result<()> {
+        let extend = extend_label(t);
+        let let_end = t.get("let_it_end");
+        let actions = [ACTION_EXTEND, extend.as_str(), ACTION_LET_END, let_end.as_str()];
+        let hints = HashMap::from([("urgency", Value::U8(1))]);
+        let id = self
+            .proxy
+            .notify(
+                "Keep Screen",
+                self.current.load(Ordering::SeqCst),
+                "",
+                "Keep Screen",
+                &expiry_body(remaining_minutes, t),
+                &actions,
+                hints,
+                0,
+            )
+            .map_err(io::Error::other)?;
+        self.current.store(id, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        let id = self.current.swap(0, Ordering::SeqCst);
+        if id != 0
+            && let Err(e) = self.proxy.close_notification(id)
+        {
+            eprintln!("关闭通知失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::i18n;
+    use crate::app::testbus::{CallLog, PrivateBus};
+    use std::time::Duration;
+
+    /// 模拟通知服务，记录收到的调用
+    #[derive(Clone, Default)]
+    struct StubNotifications {
+        calls: CallLog,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl StubNotifications {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app_name: &str,
+            replaces_id: u32,
+            _app_icon: &str,
+            _summary: &str,
+            body: &str,
+            actions: Vec<String>,
+            _hints: HashMap<String, zbus::zvariant::OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            self.calls
+                .push(format!("Notify({}, {}, {})", replaces_id, body, actions.join("|")));
+            7
+        }
+
+        fn close_notification(&self, id: u32) {
+            self.calls.push(format!("CloseNotification({})", id));
+        }
+    }
+
+    const PATH: &str = "/org/freedesktop/Notifications";
+
+    fn serve(bus: &PrivateBus, stub: &StubNotifications) -> Connection {
+        bus.serve("org.freedesktop.Notifications", PATH, stub.clone())
+    }
+
+    /// 以通知服务的身份发出 ActionInvoked 信号
+    fn invoke(service: &Connection, id: u32, action: &str) {
+        service
+            .emit_signal(
+                None::<()>,
+                PATH,
+                "org.freedesktop.Notifications",
+                "ActionInvoked",
+                &(id, action),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn extend_action_sends_extend_event() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let stub = StubNotifications::default();
+        let service = serve(&bus, &stub);
+        let (event_tx, event_rx) = crossbeam_channel::unbounded();
+        let mut notifier = FreedesktopNotifier::with_connection(bus.connect(), event_tx).unwrap();
+
+        notifier.notify_expiry(5, &i18n::english()).unwrap();
+        assert_eq!(
+            stub.calls.calls(),
+            ["Notify(0, Keeping the screen on ends in 5 minutes., extend|+15 Minutes|let_end|Let it end)"]
+        );
+
+        // 其他程序的通知不影响我们
+        invoke(&service, 3, ACTION_EXTEND);
+        invoke(&service, 7, ACTION_EXTEND);
+        assert_eq!(event_rx.recv_timeout(Duration::from_secs(5)), Ok(EXTEND_EVENT));
+        assert!(event_rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        // 操作之后通知已经消失，不再需要关闭
+        notifier.close();
+        assert_eq!(stub.calls.calls().len(), 1);
+    }
+
+    #[test]
+    fn let_it_end_and_close() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let stub = StubNotifications::default();
+        let service = serve(&bus, &stub);
+        let (event_tx, event_rx) = crossbeam_channel::unbounded();
+        let mut notifier = FreedesktopNotifier::with_connection(bus.connect(), event_tx).unwrap();
+
+        notifier.notify_expiry(2, &i18n::english()).unwrap();
+        invoke(&service, 7, ACTION_LET_END);
+        assert!(event_rx.recv_timeout(Duration::from_millis(500)).is_err());
+
+        notifier.notify_expiry(1, &i18n::english()).unwrap();
+        notifier.close();
+        let calls = stub.calls.calls();
+        assert!(calls[1].starts_with("Notify(0, Keeping the screen on ends in 1 minute."), "{:?}", calls);
+        assert_eq!(calls[2], "CloseNotification(7)");
+    }
+}
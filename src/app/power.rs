@@ -0,0 +1,148 @@
+//! 电源管理后端抽象
+//!
+//! 所有“阻止熄屏/休眠”的实现都通过 [`PowerBackend`] 接入，
+//! 事件循环只和这个 trait 打交道，不直接依赖具体平台的 API。
+
+use super::state::KeepMode;
+use serde::Deserialize;
+use std::io;
+
+#[cfg(windows)]
+mod execution_state;
+mod fallback;
+mod jiggler;
+#[cfg(target_os = "linux")]
+mod logind;
+#[cfg(test)]
+pub mod recording;
+#[cfg(target_os = "linux")]
+mod screensaver;
+#[cfg(target_os = "linux")]
+mod uinput;
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+mod xtest;
+
+#[cfg(windows)]
+pub use execution_state::ExecutionStateBackend;
+pub use fallback::FallbackChain;
+pub use jiggler::{default_injector, JigglerBackend};
+#[cfg(target_os = "linux")]
+pub use logind::LogindBackend;
+#[cfg(target_os = "linux")]
+pub use screensaver::ScreenSaverBackend;
+#[cfg(target_os = "linux")]
+pub use wayland::WaylandBackend;
+#[cfg(target_os = "linux")]
+pub use x11::X11Backend;
+
+/// 申请保持唤醒时附带的默认原因说明
+pub const REASON: &str = "Keep Screen is keeping the display on";
+
+/// 保持唤醒的后端接口
+pub trait PowerBackend: Send {
+    /// 后端名称，用于日志和菜单显示
+    fn name(&self) -> String;
+
+    /// 按指定模式申请保持唤醒；重复调用时应当替换之前的申请
+    ///
+    /// 后端无法满足该模式时返回 [`io::ErrorKind::Unsupported`]，由回退链尝试下一个后端。
+    fn acquire(&mut self, mode: KeepMode, reason: &str) -> io::Result<()>;
+
+    /// 释放之前的申请；未持有时调用应当是空操作
+    fn release(&mut self) -> io::Result<()>;
+
+    /// 查询当前持有的申请，返回其原因说明
+    fn query(&self) -> Option<&str>;
+
+    /// 从系统读回状态，确认当前的申请确实生效
+    ///
+    /// 返回 `None` 表示该后端无法确认，调用方应当视为成功。
+    fn verify(&self) -> Option<bool> {
+        None
+    }
+
+    /// 申请是否同时阻止系统休眠
+    ///
+    /// 只管理熄屏的后端返回 `false`，需要阻止休眠时由回退链另外申请一个休眠锁。
+    fn blocks_sleep(&self) -> bool {
+        true
+    }
+}
+
+/// 后端不支持某个模式时返回的错误
+#[cfg(target_os = "linux")]
+pub fn unsupported_mode(mode: KeepMode) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, format!("不支持 {:?} 模式", mode))
+}
+
+/// 可以在配置文件中指定的后端种类
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// Windows 线程执行状态
+    ExecutionState,
+    /// 会话的 `org.freedesktop.ScreenSaver` 服务
+    ScreenSaver,
+    /// Wayland 合成器的空闲抑制协议
+    Wayland,
+    /// X11 屏保扩展和 DPMS
+    X11,
+    /// systemd-logind 抑制锁
+    Logind,
+}
+
+impl BackendKind {
+    /// 尝试创建该种类的后端，当前环境不支持时返回错误
+    pub fn probe(self) -> io::Result<Box<dyn PowerBackend>> {
+        match self {
+            #[cfg(windows)]
+            BackendKind::ExecutionState => Ok(Box::new(ExecutionStateBackend::new())),
+            #[cfg(target_os = "linux")]
+            BackendKind::ScreenSaver => Ok(Box::new(ScreenSaverBackend::session()?)),
+            #[cfg(target_os = "linux")]
+            BackendKind::Wayland => Ok(Box::new(WaylandBackend::connect()?)),
+            #[cfg(target_os = "linux")]
+            BackendKind::X11 => Ok(Box::new(X11Backend::connect()?)),
+            #[cfg(target_os = "linux")]
+            BackendKind::Logind => Ok(Box::new(LogindBackend::system()?)),
+            #[allow(unreachable_patterns)]
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "当前平台不支持")),
+        }
+    }
+}
+
+/// 当前平台默认的后端顺序
+///
+/// Linux 上优先使用会话屏保服务，它才真正控制熄屏；没有时尝试 Wayland
+/// 合成器的空闲抑制协议或直接操作 X 服务器，最后退回 logind。
+pub fn default_order() -> &'static [BackendKind] {
+    if cfg!(windows) {
+        &[BackendKind::ExecutionState]
+    } else {
+        &[
+            BackendKind::ScreenSaver,
+            BackendKind::Wayland,
+            BackendKind::X11,
+            BackendKind::Logind,
+        ]
+    }
+}
+
+/// 按顺序探测可用的后端，组成回退链
+pub fn detect(order: &[BackendKind]) -> FallbackChain {
+    let mut backends = Vec::new();
+    for &kind in order {
+        match kind.probe() {
+            Ok(backend) => {
+                eprintln!("检测到可用后端: {}", backend.name());
+                backends.push(backend);
+            }
+            Err(e) => eprintln!("后端 {:?} 不可用: {}", kind, e),
+        }
+    }
+    FallbackChain::new(backends)
+}
@@ -0,0 +1,53 @@
+//! 基于 `SetThreadExecutionState` 的 Windows 后端
+
+use super::PowerBackend;
+use crate::app::state::KeepMode;
+use crate::app::win_api::{is_keep_awake_effective, set_keep_awake};
+use std::io;
+
+/// Windows 线程执行状态后端
+///
+/// 执行状态是按线程记录的，因此必须始终在同一个线程上调用。
+pub struct ExecutionStateBackend {
+    held: Option<(KeepMode, String)>,
+}
+
+impl ExecutionStateBackend {
+    pub fn new() -> Self {
+        ExecutionStateBackend { held: None }
+    }
+}
+
+impl PowerBackend for ExecutionStateBackend {
+    fn name(&self) -> String {
+        "Windows Execution State".to_string()
+    }
+
+    fn acquire(&mut self, mode: KeepMode, reason: &str) -> io::Result<()> {
+        set_keep_awake(Some(mode))?;
+        self.held = Some((mode, reason.to_string()));
+        Ok(())
+    }
+
+    fn release(&mut self) -> io::Result<()> {
+        if self.held.take().is_some() {
+            set_keep_awake(None)?;
+        }
+        Ok(())
+    }
+
+    fn query(&self) -> Option<&str> {
+        self.held.as_ref().map(|(_, reason)| reason.as_str())
+    }
+
+    fn verify(&self) -> Option<bool> {
+        let (mode, _) = self.held.as_ref()?;
+        match is_keep_awake_effective(*mode) {
+            Ok(effective) => Some(effective),
+            Err(e) => {
+                eprintln!("读取系统执行状态失败: {}", e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! 按顺序尝试多个后端的回退链
+
+use super::PowerBackend;
+use crate::app::state::KeepMode;
+use std::io;
+
+/// 依次尝试各个后端，使用第一个申请成功的（包括不支持当前模式的情况）
+///
+/// 每次申请都从最优先的后端开始，这样之前失败的后端恢复后会被重新使用。
+/// 使用的后端只管理熄屏而模式又要求阻止休眠时，另外在第一个能阻止休眠的后端上
+/// 申请 [`KeepMode::SystemOnly`]，例如屏保服务配合 logind 的 `sleep` 锁。
+pub struct FallbackChain {
+    backends: Vec<Box<dyn PowerBackend>>,
+    active: Option<usize>,
+    /// 为 `active` 补充休眠锁的后端
+    sleep_lock: Option<usize>,
+    /// 需要休眠锁但没有后端申请成功
+    sleep_lock_missing: bool,
+}
+
+impl FallbackChain {
+    pub fn new(backends: Vec<Box<dyn PowerBackend>>) -> Self {
+        FallbackChain {
+            backends,
+            active: None,
+            sleep_lock: None,
+            sleep_lock_missing: false,
+        }
+    }
+
+    /// 按模式为正在使用的后端 `active` 补充或释放休眠锁
+    fn update_sleep_lock(&mut self, active: usize, mode: KeepMode, reason: &str) {
+        let mut acquired = None;
+        if mode.keeps_system() && !self.backends[active].blocks_sleep() {
+            for i in 0..self.backends.len() {
+                if i == active || !self.backends[i].blocks_sleep() {
+                    continue;
+                }
+                match self.backends[i].acquire(KeepMode::SystemOnly, reason) {
+                    Ok(()) => {
+                        acquired = Some(i);
+                        break;
+                    }
+                    Err(e) => eprintln!("{} 后端申请休眠锁失败: {}", self.backends[i].name(), e),
+                }
+            }
+            if acquired.is_none() {
+                eprintln!("没有后端能阻止系统休眠，只保持屏幕常亮");
+            }
+        }
+        self.sleep_lock_missing = mode.keeps_system() && !self.backends[active].blocks_sleep() && acquired.is_none();
+        // 之前的休眠锁不再需要；它成为正在使用的后端时已被新的申请替换
+        if let Some(previous) = self.sleep_lock
+            && Some(previous) != acquired
+            && previous != active
+        {
+            let _ = self.backends[previous].release();
+        }
+        self.sleep_lock = acquired;
+    }
+}
+
+impl PowerBackend for FallbackChain {
+    /// 正在使用的后端名称；尚未申请时为最优先的后端
+    fn name(&self) -> String {
+        self.backends
+            .get(self.active.unwrap_or(0))
+            .map_or("None".to_string(), |backend| backend.name())
+    }
+
+    fn acquire(&mut self, mode: KeepMode, reason: &str) -> io::Result<()> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "没有可用的后端");
+        for i in 0..self.backends.len() {
+            match self.backends[i].acquire(mode, reason) {
+                Ok(()) => {
+                    if self.active != Some(i) {
+                        // 切换后端时释放之前持有的申请，避免重复持有
+                        if let Some(previous) = self.active.replace(i)
+                            && self.sleep_lock != Some(previous)
+                        {
+                            let _ = self.backends[previous].release();
+                        }
+                        eprintln!("使用后端: {}", self.backends[i].name());
+                    }
+                    self.update_sleep_lock(i, mode, reason);
+                    return Ok(());
+                }
+                Err(e) => {
+                    eprintln!("{} 后端申请失败，尝试下一个: {}", self.backends[i].name(), e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    fn release(&mut self) -> io::Result<()> {
+        self.sleep_lock_missing = false;
+        if let Some(i) = self.sleep_lock.take()
+            && let Err(e) = self.backends[i].release()
+        {
+            eprintln!("{} 后端释放休眠锁失败: {}", self.backends[i].name(), e);
+        }
+        match self.active.take() {
+            Some(i) => self.backends[i].release(),
+            None => Ok(()),
+        }
+    }
+
+    fn query(&self) -> Option<&str> {
+        self.active.and_then(|i| self.backends[i].query())
+    }
+
+    /// 正在使用的后端和休眠锁都要生效；缺少需要的休眠锁时视为未生效
+    fn verify(&self) -> Option<bool> {
+        let active = self.active?;
+        if self.sleep_lock_missing {
+            return Some(false);
+        }
+        let sleep_lock = self.sleep_lock.and_then(|i| self.backends[i].verify());
+        match (self.backends[active].verify(), sleep_lock) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (active, sleep_lock) => active.or(sleep_lock),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::power::recording::{Call, RecordingBackend};
+
+    #[test]
+    fn falls_back_when_acquire_fails() {
+        let broken = RecordingBackend::failing();
+        let working = RecordingBackend::new();
+        let mut chain = FallbackChain::new(vec![Box::new(broken.clone()), Box::new(working.clone())]);
+
+        let expected = Call::Acquire(KeepMode::DisplayAndSystem, "演示".to_string());
+        chain.acquire(KeepMode::DisplayAndSystem, "演示").unwrap();
+        assert_eq!(chain.query(), Some("演示"));
+        assert_eq!(working.calls(), [expected]);
+        assert_eq!(broken.calls(), working.calls());
+
+        chain.release().unwrap();
+        assert_eq!(chain.query(), None);
+        assert_eq!(working.calls().last(), Some(&Call::Release));
+        assert_eq!(broken.calls().len(), 1);
+    }
+
+    #[test]
+    fn pairs_display_backend_with_sleep_lock() {
+        let display = RecordingBackend::display_only();
+        let sleep = RecordingBackend::new();
+        let mut chain = FallbackChain::new(vec![Box::new(display.clone()), Box::new(sleep.clone())]);
+
+        chain.acquire(KeepMode::DisplayAndSystem, "演示").unwrap();
+        assert_eq!(chain.name(), "Recording");
+        assert_eq!(display.calls(), [Call::Acquire(KeepMode::DisplayAndSystem, "演示".to_string())]);
+        assert_eq!(sleep.calls(), [Call::Acquire(KeepMode::SystemOnly, "演示".to_string())]);
+        assert_eq!(chain.verify(), Some(true));
+
+        // 只保持屏幕时不再需要休眠锁
+        chain.acquire(KeepMode::DisplayOnly, "演示").unwrap();
+        assert_eq!(sleep.calls().last(), Some(&Call::Release));
+
+        chain.acquire(KeepMode::DisplayAndSystem, "演示").unwrap();
+        chain.release().unwrap();
+        assert_eq!(display.calls().last(), Some(&Call::Release));
+        assert_eq!(sleep.calls().last(), Some(&Call::Release));
+    }
+
+    #[test]
+    fn reports_missing_sleep_lock() {
+        let mut chain = FallbackChain::new(vec![
+            Box::new(RecordingBackend::display_only()),
+            Box::new(RecordingBackend::failing()),
+        ]);
+        chain.acquire(KeepMode::DisplayAndSystem, "演示").unwrap();
+        assert_eq!(chain.verify(), Some(false));
+        chain.acquire(KeepMode::DisplayOnly, "演示").unwrap();
+        assert_eq!(chain.verify(), Some(true));
+    }
+
+    /// 默认顺序中屏保服务排在 logind 之前，也要由 logind 阻止休眠
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn default_chain_takes_logind_sleep_lock() {
+        use crate::app::power::{LogindBackend, ScreenSaverBackend};
+        use crate::app::testbus::{PrivateBus, StubLogin1, StubScreenSaver};
+
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let screen_saver = StubScreenSaver::default();
+        let _screen_saver_service = screen_saver.serve(&bus);
+        let login1 = StubLogin1::default();
+        let _login1_service = login1.serve(&bus);
+
+        let mut chain = FallbackChain::new(vec![
+            Box::new(ScreenSaverBackend::with_connection(bus.connect()).unwrap()),
+            Box::new(LogindBackend::with_connection(bus.connect())),
+        ]);
+        chain.acquire(KeepMode::DisplayAndSystem, "测试").unwrap();
+        assert_eq!(chain.name(), "org.freedesktop.ScreenSaver");
+        assert_eq!(screen_saver.calls.calls(), ["Inhibit(测试) -> 1"]);
+        assert_eq!(login1.calls.calls(), ["Inhibit(sleep, 测试, block)"]);
+        assert_eq!(chain.verify(), Some(true));
+    }
+
+    #[test]
+    fn empty_chain_reports_error() {
+        let mut chain = FallbackChain::new(Vec::new());
+        assert_eq!(chain.name(), "None");
+        assert!(chain.acquire(KeepMode::SystemOnly, "演示").is_err());
+        assert!(chain.release().is_ok());
+    }
+}
@@ -0,0 +1,217 @@
+//! 模拟输入的后备方案
+//!
+//! 部分受管机器的策略会忽略执行状态/抑制锁请求，空闲后照样锁屏。
+//! 这个后端在持有期间周期性注入一个无害的输入事件，让系统认为用户仍在操作。
+
+use super::PowerBackend;
+use crate::app::state::KeepMode;
+use crossbeam_channel::Sender;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// 注入无害输入事件的方式
+pub trait InputInjector: Send {
+    /// 注入一次输入事件，重置系统的空闲计时
+    fn inject(&mut self) -> io::Result<()>;
+}
+
+/// 正在运行的注入线程：停止信号的发送端和线程句柄
+type Worker = (Sender<()>, thread::JoinHandle<Box<dyn InputInjector>>);
+
+/// 在内层后端之外周期性模拟输入
+///
+/// 内层后端申请失败不影响模拟输入，这正是需要这个后备方案的场景。
+pub struct JigglerBackend {
+    inner: Box<dyn PowerBackend>,
+    injector: Option<Box<dyn InputInjector>>,
+    interval: Duration,
+    worker: Option<Worker>,
+    reason: Option<String>,
+}
+
+impl JigglerBackend {
+    pub fn new(inner: Box<dyn PowerBackend>, injector: Box<dyn InputInjector>, interval: Duration) -> Self {
+        JigglerBackend {
+            inner,
+            injector: Some(injector),
+            interval,
+            worker: None,
+            reason: None,
+        }
+    }
+
+    /// 启动注入线程，线程结束时交还注入器
+    fn start(&mut self) {
+        let Some(mut injector) = self.injector.take() else {
+            return;
+        };
+        let (shutdown_tx, shutdown_rx) = crossbeam_channel::unbounded::<()>();
+        let interval = self.interval;
+        let handle = thread::spawn(move || {
+            // 等待超时即到了注入的时间，收到消息或发送端断开则停止
+            while shutdown_rx.recv_timeout(interval).is_err_and(|e| e.is_timeout()) {
+                if let Err(e) = injector.inject() {
+                    eprintln!("模拟输入失败: {}", e);
+                }
+            }
+            injector
+        });
+        self.worker = Some((shutdown_tx, handle));
+    }
+
+    fn stop(&mut self) {
+        if let Some((shutdown_tx, handle)) = self.worker.take() {
+            let _ = shutdown_tx.send(());
+            match handle.join() {
+                Ok(injector) => self.injector = Some(injector),
+                Err(_) => eprintln!("模拟输入线程异常退出"),
+            }
+        }
+    }
+}
+
+impl PowerBackend for JigglerBackend {
+    fn name(&self) -> String {
+        format!("{} + input simulation", self.inner.name())
+    }
+
+    fn acquire(&mut self, mode: KeepMode, reason: &str) -> io::Result<()> {
+        if let Err(e) = self.inner.acquire(mode, reason) {
+            eprintln!("{} 后端申请失败，仅依靠模拟输入: {}", self.inner.name(), e);
+        }
+        // 模拟输入只能阻止熄屏，只阻止休眠时不需要
+        if !mode.keeps_display() {
+            self.stop();
+        } else if self.worker.is_none() {
+            self.start();
+        }
+        self.reason = Some(reason.to_string());
+        Ok(())
+    }
+
+    fn release(&mut self) -> io::Result<()> {
+        self.stop();
+        self.reason = None;
+        self.inner.release()
+    }
+
+    fn query(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    /// 内层后端未生效时，模拟输入仍在工作，因此只在内层确认成功时才报告结果
+    fn verify(&self) -> Option<bool> {
+        self.inner.verify().filter(|&effective| effective)
+    }
+
+    fn blocks_sleep(&self) -> bool {
+        self.inner.blocks_sleep()
+    }
+}
+
+impl Drop for JigglerBackend {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// 通过 `SendInput` 发送零距离的鼠标移动
+#[cfg(windows)]
+struct SendInputInjector;
+
+#[cfg(windows)]
+impl InputInjector for SendInputInjector {
+    fn inject(&mut self) -> io::Result<()> {
+        crate::app::win_api::send_idle_input()
+    }
+}
+
+/// 创建当前平台可用的输入注入器
+pub fn default_injector() -> io::Result<Box<dyn InputInjector>> {
+    #[cfg(windows)]
+    return Ok(Box::new(SendInputInjector));
+
+    #[cfg(target_os = "linux")]
+    return match super::xtest::XTestInjector::connect() {
+        Ok(injector) => Ok(Box::new(injector)),
+        Err(e) => {
+            eprintln!("XTest 不可用，改用 uinput: {}", e);
+            Ok(Box::new(super::uinput::UinputInjector::create()?))
+        }
+    };
+
+    #[allow(unreachable_code)]
+    Err(io::Error::new(io::ErrorKind::Unsupported, "当前平台不支持模拟输入"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::power::recording::{Call, RecordingBackend};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// 只计数的注入器
+    struct CountingInjector(Arc<AtomicUsize>);
+
+    impl InputInjector for CountingInjector {
+        fn inject(&mut self) -> io::Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn injects_periodically_until_released() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let inner = RecordingBackend::failing();
+        let mut backend = JigglerBackend::new(
+            Box::new(inner.clone()),
+            Box::new(CountingInjector(count.clone())),
+            Duration::from_millis(10),
+        );
+
+        // 内层后端失败时仍然算作持有
+        backend.acquire(KeepMode::DisplayAndSystem, "演示").unwrap();
+        assert_eq!(backend.query(), Some("演示"));
+        thread::sleep(Duration::from_millis(100));
+        backend.release().unwrap();
+        let injected = count.load(Ordering::SeqCst);
+        assert!(injected >= 2, "只注入了 {} 次", injected);
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), injected);
+        assert_eq!(inner.calls().last(), Some(&Call::Release));
+
+        // 注入器在释放后被收回，可以再次启动
+        backend.acquire(KeepMode::DisplayAndSystem, "演示").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(count.load(Ordering::SeqCst) > injected);
+    }
+
+    #[test]
+    fn skips_input_when_display_may_sleep() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut backend = JigglerBackend::new(
+            Box::new(RecordingBackend::new()),
+            Box::new(CountingInjector(count.clone())),
+            Duration::from_millis(10),
+        );
+        assert_eq!(backend.name(), "Recording + input simulation");
+
+        backend.acquire(KeepMode::SystemOnly, "演示").unwrap();
+        assert_eq!(backend.query(), Some("演示"));
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        // 切换到需要保持屏幕的模式后开始模拟输入，切回后停止
+        backend.acquire(KeepMode::DisplayAndSystem, "演示").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        backend.acquire(KeepMode::SystemOnly, "演示").unwrap();
+        let injected = count.load(Ordering::SeqCst);
+        assert!(injected > 0);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), injected);
+    }
+}
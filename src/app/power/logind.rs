@@ -0,0 +1,162 @@
+//! 基于 systemd-logind 抑制锁的 Linux 后端
+
+use super::PowerBackend;
+use crate::app::state::KeepMode;
+use std::io;
+use zbus::blocking::Connection;
+use zbus::zvariant::OwnedFd;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1",
+    gen_async = false,
+    blocking_name = "ManagerProxy"
+)]
+trait Manager {
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+    fn list_inhibitors(&self) -> zbus::Result<Vec<Inhibitor>>;
+}
+
+/// `ListInhibitors` 返回的一项：(what, who, why, mode, uid, pid)
+type Inhibitor = (String, String, String, String, u32, u32);
+
+/// 申请时使用的 who 字段，读回时用它识别我们的锁
+const WHO: &str = "Keep Screen";
+
+/// 当前持有的锁
+struct Lock {
+    _fd: OwnedFd,
+    what: &'static str,
+    reason: String,
+}
+
+/// 通过 `org.freedesktop.login1.Manager.Inhibit` 获取 idle/sleep 抑制锁
+///
+/// `idle` 阻止会话进入空闲（熄屏），`sleep` 阻止系统休眠。
+///
+/// logind 在返回的文件描述符被关闭时自动释放锁，因此只要持有它即可。
+pub struct LogindBackend {
+    connection: Connection,
+    lock: Option<Lock>,
+}
+
+impl LogindBackend {
+    /// 连接系统总线
+    pub fn system() -> io::Result<Self> {
+        Ok(Self::with_connection(Connection::system().map_err(io::Error::other)?))
+    }
+
+    /// 使用指定的总线连接，便于在测试中连接私有总线
+    pub fn with_connection(connection: Connection) -> Self {
+        LogindBackend { connection, lock: None }
+    }
+}
+
+impl PowerBackend for LogindBackend {
+    fn name(&self) -> String {
+        "systemd-logind".to_string()
+    }
+
+    fn acquire(&mut self, mode: KeepMode, reason: &str) -> io::Result<()> {
+        let what = match mode {
+            KeepMode::DisplayAndSystem => "sleep:idle",
+            KeepMode::SystemOnly => "sleep",
+            KeepMode::DisplayOnly => "idle",
+        };
+        let manager = ManagerProxy::new(&self.connection).map_err(io::Error::other)?;
+        let fd = manager
+            .inhibit(what, WHO, reason, "block")
+            .map_err(io::Error::other)?;
+        // 先拿到新锁再替换旧锁，避免中间出现空档
+        self.lock = Some(Lock {
+            _fd: fd,
+            what,
+            reason: reason.to_string(),
+        });
+        Ok(())
+    }
+
+    fn release(&mut self) -> io::Result<()> {
+        // 关闭文件描述符即释放锁
+        self.lock = None;
+        Ok(())
+    }
+
+    fn query(&self) -> Option<&str> {
+        self.lock.as_ref().map(|lock| lock.reason.as_str())
+    }
+
+    /// 在 `ListInhibitors` 中查找本进程持有的锁
+    fn verify(&self) -> Option<bool> {
+        let lock = self.lock.as_ref()?;
+        let inhibitors = ManagerProxy::new(&self.connection).and_then(|manager| manager.list_inhibitors());
+        match inhibitors {
+            Ok(inhibitors) => Some(inhibitors.iter().any(|(what, who, _, _, _, pid)| {
+                who == WHO && *pid == std::process::id() && what == lock.what
+            })),
+            Err(e) => {
+                eprintln!("读取 logind 抑制锁列表失败: {}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testbus::{PrivateBus, StubLogin1};
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    /// 锁的另一端是否已被关闭
+    fn is_closed(peer: &mut UnixStream) -> bool {
+        peer.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        matches!(peer.read(&mut [0u8; 1]), Ok(0))
+    }
+
+    #[test]
+    fn holds_inhibitor_fd_until_release() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let stub = StubLogin1::default();
+        let _service = stub.serve(&bus);
+
+        let mut backend = LogindBackend::with_connection(bus.connect());
+        assert_eq!(backend.verify(), None);
+        backend.acquire(KeepMode::DisplayAndSystem, "测试").unwrap();
+        assert_eq!(backend.query(), Some("测试"));
+        assert_eq!(backend.verify(), Some(true));
+        assert!(!is_closed(&mut stub.peers.lock().unwrap()[0]));
+
+        // 切换模式时先拿到新锁，再关闭旧锁
+        backend.acquire(KeepMode::SystemOnly, "测试").unwrap();
+        assert!(is_closed(&mut stub.peers.lock().unwrap()[0]));
+        assert!(!is_closed(&mut stub.peers.lock().unwrap()[1]));
+        assert_eq!(
+            stub.calls.calls(),
+            ["Inhibit(sleep:idle, 测试, block)", "Inhibit(sleep, 测试, block)"]
+        );
+
+        backend.release().unwrap();
+        assert_eq!(backend.query(), None);
+        assert!(is_closed(&mut stub.peers.lock().unwrap()[1]));
+    }
+
+    #[test]
+    fn verify_detects_missing_lock() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let stub = StubLogin1::dropping_locks();
+        let _service = stub.serve(&bus);
+
+        let mut backend = LogindBackend::with_connection(bus.connect());
+        backend.acquire(KeepMode::SystemOnly, "测试").unwrap();
+        assert_eq!(backend.verify(), Some(false));
+    }
+}
@@ -0,0 +1,91 @@
+//! 仅记录调用的内存后端，用于单元测试
+
+use super::PowerBackend;
+use crate::app::state::KeepMode;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// 后端收到的调用
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Call {
+    Acquire(KeepMode, String),
+    Release,
+}
+
+/// 记录所有调用的后端，测试可以通过 [`RecordingBackend::calls`] 观察
+#[derive(Clone, Default)]
+pub struct RecordingBackend {
+    calls: Arc<Mutex<Vec<Call>>>,
+    reason: Option<String>,
+    fail_acquire: bool,
+    unverified: bool,
+    display_only: bool,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 让之后的每次 acquire 都返回错误
+    pub fn failing() -> Self {
+        RecordingBackend {
+            fail_acquire: true,
+            ..Self::default()
+        }
+    }
+
+    /// 申请成功，但读回时找不到申请
+    pub fn unverified() -> Self {
+        RecordingBackend {
+            unverified: true,
+            ..Self::default()
+        }
+    }
+
+    /// 像屏保服务一样只管理熄屏，不阻止休眠
+    pub fn display_only() -> Self {
+        RecordingBackend {
+            display_only: true,
+            ..Self::default()
+        }
+    }
+
+    /// 返回到目前为止记录的调用
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl PowerBackend for RecordingBackend {
+    fn name(&self) -> String {
+        "Recording".to_string()
+    }
+
+    fn acquire(&mut self, mode: KeepMode, reason: &str) -> io::Result<()> {
+        self.calls.lock().unwrap().push(Call::Acquire(mode, reason.to_string()));
+        if self.fail_acquire {
+            return Err(io::Error::other("模拟的申请失败"));
+        }
+        self.reason = Some(reason.to_string());
+        Ok(())
+    }
+
+    fn release(&mut self) -> io::Result<()> {
+        self.calls.lock().unwrap().push(Call::Release);
+        self.reason = None;
+        Ok(())
+    }
+
+    fn query(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    fn verify(&self) -> Option<bool> {
+        self.reason.as_ref().map(|_| !self.unverified)
+    }
+
+    fn blocks_sleep(&self) -> bool {
+        !self.display_only
+    }
+}
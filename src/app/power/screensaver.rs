@@ -0,0 +1,259 @@
+//! 基于 `org.freedesktop.ScreenSaver` 的 Linux 后端
+//!
+//! 会话的屏保服务控制着熄屏，相当于 Windows 上的 `ES_DISPLAY_REQUIRED`。
+
+use super::{unsupported_mode, PowerBackend};
+use crate::app::state::KeepMode;
+use std::io;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use zbus::blocking::Connection;
+use zbus::blocking::fdo::DBusProxy;
+
+const SERVICE: &str = "org.freedesktop.ScreenSaver";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.ScreenSaver",
+    default_service = "org.freedesktop.ScreenSaver",
+    default_path = "/org/freedesktop/ScreenSaver",
+    gen_async = false,
+    blocking_name = "ScreenSaverProxy"
+)]
+trait ScreenSaver {
+    fn inhibit(&self, application_name: &str, reason_for_inhibit: &str) -> zbus::Result<u32>;
+
+    #[zbus(name = "UnInhibit")]
+    fn un_inhibit(&self, cookie: u32) -> zbus::Result<()>;
+}
+
+/// 当前的抑制请求
+struct Inhibition {
+    cookie: u32,
+    reason: String,
+}
+
+/// 通过会话屏保服务的 Inhibit/UnInhibit 阻止熄屏
+///
+/// 桌面环境会同时暂停空闲时的自动休眠，但无法单独阻止系统休眠。
+/// 屏保服务重启或更换所有者后，旧的 cookie 随之失效，
+/// 后台线程会监听所有者变化并重新申请。后端丢弃时关闭连接，监听线程随之结束。
+pub struct ScreenSaverBackend {
+    connection: Connection,
+    inhibition: Arc<Mutex<Option<Inhibition>>>,
+    reason: Option<String>,
+    watcher: Option<thread::JoinHandle<()>>,
+}
+
+impl ScreenSaverBackend {
+    /// 连接会话总线，屏保服务不存在时返回错误
+    pub fn session() -> io::Result<Self> {
+        let connection = Connection::session().map_err(io::Error::other)?;
+        if !service_has_owner(&connection)? {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "会话中没有屏保服务"));
+        }
+        Self::with_connection(connection)
+    }
+
+    /// 使用指定的总线连接，便于在测试中连接私有总线
+    pub fn with_connection(connection: Connection) -> io::Result<Self> {
+        let inhibition = Arc::new(Mutex::new(None));
+        let watcher = watch_owner(connection.clone(), Arc::downgrade(&inhibition))?;
+        Ok(ScreenSaverBackend {
+            connection,
+            inhibition,
+            reason: None,
+            watcher: Some(watcher),
+        })
+    }
+}
+
+impl PowerBackend for ScreenSaverBackend {
+    fn name(&self) -> String {
+        "org.freedesktop.ScreenSaver".to_string()
+    }
+
+    fn acquire(&mut self, mode: KeepMode, reason: &str) -> io::Result<()> {
+        if !mode.keeps_display() {
+            return Err(unsupported_mode(mode));
+        }
+        let proxy = ScreenSaverProxy::new(&self.connection).map_err(io::Error::other)?;
+        let cookie = proxy.inhibit("Keep Screen", reason).map_err(io::Error::other)?;
+        // 只在替换 cookie 时持有锁，D-Bus 调用可能阻塞
+        let old = self.inhibition.lock().unwrap().replace(Inhibition {
+            cookie,
+            reason: reason.to_string(),
+        });
+        if let Some(old) = old {
+            let _ = proxy.un_inhibit(old.cookie);
+        }
+        self.reason = Some(reason.to_string());
+        Ok(())
+    }
+
+    fn release(&mut self) -> io::Result<()> {
+        self.reason = None;
+        let Some(old) = self.inhibition.lock().unwrap().take() else {
+            return Ok(());
+        };
+        ScreenSaverProxy::new(&self.connection)
+            .and_then(|proxy| proxy.un_inhibit(old.cookie))
+            .map_err(io::Error::other)
+    }
+
+    fn query(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    /// 确认屏保服务仍然存在；服务退出后 cookie 随之失效，要等新的所有者出现后才会重新申请
+    fn verify(&self) -> Option<bool> {
+        self.reason.as_ref()?;
+        match service_has_owner(&self.connection) {
+            Ok(has_owner) => Some(has_owner),
+            Err(e) => {
+                eprintln!("查询屏保服务失败: {}", e);
+                None
+            }
+        }
+    }
+
+    fn blocks_sleep(&self) -> bool {
+        false
+    }
+}
+
+/// 总线上是否有屏保服务
+fn service_has_owner(connection: &Connection) -> io::Result<bool> {
+    let dbus = DBusProxy::new(connection).map_err(io::Error::other)?;
+    let service = SERVICE.try_into().map_err(io::Error::other)?;
+    dbus.name_has_owner(service).map_err(io::Error::other)
+}
+
+impl Drop for ScreenSaverBackend {
+    fn drop(&mut self) {
+        let _ = self.release();
+        // 关闭连接后所有者变化的消息流随之结束，监听线程退出
+        let _ = self.connection.clone().close();
+        if let Some(watcher) = self.watcher.take() {
+            let _ = watcher.join();
+        }
+    }
+}
+
+/// 在后台监听屏保服务的所有者变化，出现新的所有者时重新申请
+///
+/// 连接关闭或后端被丢弃时线程结束。
+fn watch_owner(
+    connection: Connection,
+    inhibition: Weak<Mutex<Option<Inhibition>>>,
+) -> io::Result<thread::JoinHandle<()>> {
+    let proxy = ScreenSaverProxy::new(&connection).map_err(io::Error::other)?;
+    let owners = proxy.inner().receive_owner_changed().map_err(io::Error::other)?;
+    Ok(thread::spawn(move || {
+        for owner in owners {
+            // 后端已被丢弃，结束监听
+            let Some(inhibition) = inhibition.upgrade() else {
+                break;
+            };
+            if owner.is_none() {
+                continue;
+            }
+            // 只在读取和替换 cookie 时持有锁，申请期间后端可以正常释放或重新申请
+            let Some((stale, reason)) = inhibition
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|current| (current.cookie, current.reason.clone()))
+            else {
+                continue;
+            };
+            let cookie = match proxy.inhibit("Keep Screen", &reason) {
+                Ok(cookie) => cookie,
+                Err(e) => {
+                    eprintln!("屏保服务变化后重新申请失败: {}", e);
+                    continue;
+                }
+            };
+            let mut current = inhibition.lock().unwrap();
+            match current.as_mut() {
+                Some(current) if current.cookie == stale => current.cookie = cookie,
+                // 申请期间后端已经释放或重新申请，新的 cookie 不再需要
+                _ => {
+                    drop(current);
+                    let _ = proxy.un_inhibit(cookie);
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testbus::{PrivateBus, StubScreenSaver};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn inhibit_and_uninhibit_with_cookie() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let stub = StubScreenSaver::default();
+        let _service = stub.serve(&bus);
+
+        let mut backend = ScreenSaverBackend::with_connection(bus.connect()).unwrap();
+        assert!(backend.acquire(KeepMode::SystemOnly, "演示").is_err());
+        backend.acquire(KeepMode::DisplayOnly, "演示").unwrap();
+        assert_eq!(backend.query(), Some("演示"));
+        backend.release().unwrap();
+        assert_eq!(backend.query(), None);
+        assert_eq!(stub.calls.wait_for(2), ["Inhibit(演示) -> 1", "UnInhibit(1)"]);
+    }
+
+    #[test]
+    fn reinhibits_when_service_owner_changes() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let first = StubScreenSaver::default();
+        let service = first.serve(&bus);
+
+        let mut backend = ScreenSaverBackend::with_connection(bus.connect()).unwrap();
+        backend.acquire(KeepMode::DisplayAndSystem, "演示").unwrap();
+        assert_eq!(first.calls.wait_for(1), ["Inhibit(演示) -> 1"]);
+        assert_eq!(backend.verify(), Some(true));
+
+        // 屏保服务重启：旧连接断开，新的所有者接管名称
+        drop(service);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while backend.verify() != Some(false) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(backend.verify(), Some(false));
+        let second = StubScreenSaver::default();
+        let _service = second.serve(&bus);
+        assert_eq!(second.calls.wait_for(1), ["Inhibit(演示) -> 1"]);
+        assert_eq!(backend.verify(), Some(true));
+
+        backend.release().unwrap();
+        assert_eq!(second.calls.wait_for(2), ["Inhibit(演示) -> 1", "UnInhibit(1)"]);
+    }
+
+    #[test]
+    fn drop_releases_and_stops_watching_owner() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let stub = StubScreenSaver::default();
+        let _service = stub.serve(&bus);
+
+        let mut backend = ScreenSaverBackend::with_connection(bus.connect()).unwrap();
+        backend.acquire(KeepMode::DisplayOnly, "演示").unwrap();
+        let (done_tx, done_rx) = crossbeam_channel::bounded(1);
+        thread::spawn(move || {
+            drop(backend);
+            let _ = done_tx.send(());
+        });
+        assert!(done_rx.recv_timeout(Duration::from_secs(5)).is_ok(), "监听线程没有结束");
+        assert_eq!(stub.calls.wait_for(2), ["Inhibit(演示) -> 1", "UnInhibit(1)"]);
+    }
+}
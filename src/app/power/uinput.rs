@@ -0,0 +1,109 @@
+//! 通过 uinput 虚拟键盘模拟输入
+//!
+//! 在 Wayland 等 XTest 不可用的会话中使用，需要对 `/dev/uinput` 有写权限。
+
+use super::jiggler::InputInjector;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+
+// 来自 <linux/uinput.h> 和 <linux/input-event-codes.h>
+const UI_SET_EVBIT: u64 = 0x4004_5564;
+const UI_SET_KEYBIT: u64 = 0x4004_5565;
+const UI_DEV_CREATE: u64 = 0x5501;
+const UI_DEV_DESTROY: u64 = 0x5502;
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const SYN_REPORT: u16 = 0;
+const KEY_F15: u16 = 185;
+const BUS_VIRTUAL: u16 = 0x06;
+
+/// 旧式的 `struct uinput_user_dev`，写入设备文件以描述虚拟设备
+#[repr(C)]
+struct UinputUserDev {
+    name: [u8; 80],
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+    ff_effects_max: u32,
+    absmax: [i32; 64],
+    absmin: [i32; 64],
+    absfuzz: [i32; 64],
+    absflat: [i32; 64],
+}
+
+/// 只有 F15 一个按键的虚拟键盘，F15 几乎不会被任何程序绑定
+pub struct UinputInjector {
+    device: File,
+}
+
+impl UinputInjector {
+    /// 创建虚拟键盘
+    pub fn create() -> io::Result<Self> {
+        let mut device = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/uinput")?;
+        ioctl(&device, UI_SET_EVBIT, EV_KEY.into())?;
+        ioctl(&device, UI_SET_KEYBIT, KEY_F15.into())?;
+
+        let mut dev = UinputUserDev {
+            name: [0; 80],
+            bustype: BUS_VIRTUAL,
+            vendor: 0,
+            product: 0,
+            version: 1,
+            ff_effects_max: 0,
+            absmax: [0; 64],
+            absmin: [0; 64],
+            absfuzz: [0; 64],
+            absflat: [0; 64],
+        };
+        let name = b"Keep Screen virtual keyboard";
+        dev.name[..name.len()].copy_from_slice(name);
+        device.write_all(as_bytes(&dev))?;
+        ioctl(&device, UI_DEV_CREATE, 0)?;
+        Ok(UinputInjector { device })
+    }
+
+    fn emit(&mut self, type_: u16, code: u16, value: i32) -> io::Result<()> {
+        let event = libc::input_event {
+            time: libc::timeval { tv_sec: 0, tv_usec: 0 },
+            type_,
+            code,
+            value,
+        };
+        self.device.write_all(as_bytes(&event))
+    }
+}
+
+impl InputInjector for UinputInjector {
+    fn inject(&mut self) -> io::Result<()> {
+        self.emit(EV_KEY, KEY_F15, 1)?;
+        self.emit(EV_SYN, SYN_REPORT, 0)?;
+        self.emit(EV_KEY, KEY_F15, 0)?;
+        self.emit(EV_SYN, SYN_REPORT, 0)
+    }
+}
+
+impl Drop for UinputInjector {
+    fn drop(&mut self) {
+        let _ = ioctl(&self.device, UI_DEV_DESTROY, 0);
+    }
+}
+
+fn ioctl(device: &File, request: u64, value: libc::c_int) -> io::Result<()> {
+    // SAFETY: 文件描述符有效，这些请求只接受整数参数
+    if unsafe { libc::ioctl(device.as_raw_fd(), request as _, value) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// 把内核结构体按原样写出
+fn as_bytes<T>(value: &T) -> &[u8] {
+    // SAFETY: 仅用于 repr(C) 的纯数据结构体
+    unsafe { std::slice::from_raw_parts((value as *const T).cast::<u8>(), size_of::<T>()) }
+}
@@ -0,0 +1,150 @@
+//! 基于 `zwp_idle_inhibit_manager_v1` 的 Wayland 后端
+
+use super::{unsupported_mode, PowerBackend};
+use crate::app::state::KeepMode;
+use std::io;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_compositor::WlCompositor, wl_registry::WlRegistry, wl_surface::WlSurface};
+use wayland_client::{delegate_noop, Connection, Dispatch, EventQueue, QueueHandle};
+use wayland_protocols::wp::idle_inhibit::zv1::client::zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1;
+use wayland_protocols::wp::idle_inhibit::zv1::client::zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1;
+
+/// 事件队列的状态，这些对象都不需要处理事件
+struct Globals;
+
+impl Dispatch<WlRegistry, GlobalListContents> for Globals {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as wayland_client::Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(Globals: WlCompositor);
+delegate_noop!(Globals: ignore WlSurface);
+delegate_noop!(Globals: ZwpIdleInhibitManagerV1);
+delegate_noop!(Globals: ZwpIdleInhibitorV1);
+
+/// 为一个不可见的小 surface 创建空闲抑制器，阻止合成器熄屏
+///
+/// 抑制空闲同时会推迟空闲时的自动休眠，但无法单独阻止系统休眠。
+/// Wayland 上只有附着在 surface 上的抑制器才可靠。sway 等 wlroots
+/// 合成器把没有关联窗口的 surface 视为可见，因此不需要真正映射它。
+pub struct WaylandBackend {
+    connection: Connection,
+    queue: EventQueue<Globals>,
+    manager: ZwpIdleInhibitManagerV1,
+    surface: WlSurface,
+    inhibitor: Option<(ZwpIdleInhibitorV1, String)>,
+}
+
+impl WaylandBackend {
+    /// 连接 `WAYLAND_DISPLAY` 指定的合成器
+    pub fn connect() -> io::Result<Self> {
+        Self::with_connection(Connection::connect_to_env().map_err(io::Error::other)?)
+    }
+
+    /// 使用指定的合成器连接，便于在测试中连接无头合成器
+    pub fn with_connection(connection: Connection) -> io::Result<Self> {
+        let (globals, queue) = registry_queue_init::<Globals>(&connection).map_err(io::Error::other)?;
+        let qh = queue.handle();
+        let compositor: WlCompositor = globals.bind(&qh, 1..=4, ()).map_err(io::Error::other)?;
+        let manager: ZwpIdleInhibitManagerV1 = globals.bind(&qh, 1..=1, ()).map_err(io::Error::other)?;
+        let surface = compositor.create_surface(&qh, ());
+        surface.commit();
+        let mut backend = WaylandBackend {
+            connection,
+            queue,
+            manager,
+            surface,
+            inhibitor: None,
+        };
+        backend.roundtrip()?;
+        Ok(backend)
+    }
+
+    /// 发送请求并等待合成器处理完毕，以便及时发现协议错误
+    fn roundtrip(&mut self) -> io::Result<()> {
+        self.queue.roundtrip(&mut Globals).map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+impl PowerBackend for WaylandBackend {
+    fn name(&self) -> String {
+        "Wayland idle-inhibit".to_string()
+    }
+
+    fn acquire(&mut self, mode: KeepMode, reason: &str) -> io::Result<()> {
+        if !mode.keeps_display() {
+            return Err(unsupported_mode(mode));
+        }
+        if let Some((_, held_reason)) = self.inhibitor.as_mut() {
+            // 抑制器本身不携带原因，只需更新记录
+            *held_reason = reason.to_string();
+            return Ok(());
+        }
+        let inhibitor = self.manager.create_inhibitor(&self.surface, &self.queue.handle(), ());
+        self.surface.commit();
+        self.inhibitor = Some((inhibitor, reason.to_string()));
+        self.roundtrip()
+    }
+
+    fn release(&mut self) -> io::Result<()> {
+        let Some((inhibitor, _)) = self.inhibitor.take() else {
+            return Ok(());
+        };
+        inhibitor.destroy();
+        self.surface.commit();
+        self.connection.flush().map_err(io::Error::other)
+    }
+
+    fn query(&self) -> Option<&str> {
+        self.inhibitor.as_ref().map(|(_, reason)| reason.as_str())
+    }
+
+    /// 确认与合成器的连接仍然正常，连接断开或出现协议错误时抑制器随之失效
+    fn verify(&self) -> Option<bool> {
+        self.inhibitor.as_ref()?;
+        Some(self.connection.flush().is_ok() && self.connection.protocol_error().is_none())
+    }
+
+    fn blocks_sleep(&self) -> bool {
+        false
+    }
+}
+
+impl Drop for WaylandBackend {
+    fn drop(&mut self) {
+        let _ = self.release();
+        self.surface.destroy();
+        self.manager.destroy();
+        let _ = self.connection.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testdisplay::Weston;
+
+    #[test]
+    #[ignore = "需要 weston"]
+    fn creates_and_destroys_inhibitor() {
+        let weston = Weston::start();
+        let connection = Connection::from_socket(weston.connect()).unwrap();
+        let mut backend = WaylandBackend::with_connection(connection).unwrap();
+        backend.acquire(KeepMode::DisplayOnly, "演示").unwrap();
+        assert_eq!(backend.query(), Some("演示"));
+        assert_eq!(backend.verify(), Some(true));
+        backend.release().unwrap();
+        assert_eq!(backend.query(), None);
+        // 释放后再次申请应当创建新的抑制器，而不是触发协议错误
+        backend.acquire(KeepMode::DisplayAndSystem, "演示").unwrap();
+        assert_eq!(backend.query(), Some("演示"));
+    }
+}
@@ -0,0 +1,204 @@
+//! 基于 X11 屏保扩展和 DPMS 的 Linux 后端
+//!
+//! 适用于没有 D-Bus 屏保服务的 X11 会话（极简窗口管理器、kiosk 等）。
+
+use super::{unsupported_mode, PowerBackend};
+use crate::app::state::KeepMode;
+use std::io;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::dpms::{self, ConnectionExt as _};
+use x11rb::protocol::screensaver::{self, ConnectionExt as _};
+use x11rb::rust_connection::RustConnection;
+
+/// 申请前的 DPMS 设置，释放时恢复
+struct DpmsSettings {
+    enabled: bool,
+    standby: u16,
+    suspend: u16,
+    off: u16,
+}
+
+/// 通过 `XScreenSaverSuspend` 暂停屏保，并在持有期间关闭 DPMS
+///
+/// 只能控制显示，不能单独阻止系统休眠；但屏保不启动时会话也不会被视为空闲。
+/// 屏保暂停只对发出请求的连接有效，连接断开后服务器会自动恢复，
+/// 因此后端必须一直持有这个连接。
+pub struct X11Backend {
+    connection: RustConnection,
+    has_dpms: bool,
+    saved_dpms: Option<DpmsSettings>,
+    reason: Option<String>,
+}
+
+impl X11Backend {
+    /// 连接 `DISPLAY` 指定的 X 服务器
+    pub fn connect() -> io::Result<Self> {
+        Self::connect_to(None)
+    }
+
+    /// 连接指定的 X 服务器，便于在测试中连接 Xvfb
+    pub fn connect_to(display: Option<&str>) -> io::Result<Self> {
+        let (connection, _) = x11rb::connect(display).map_err(io::Error::other)?;
+        let has_extension = |name| {
+            connection
+                .extension_information(name)
+                .map(|info| info.is_some())
+                .map_err(io::Error::other)
+        };
+        if !has_extension(screensaver::X11_EXTENSION_NAME)? {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "X 服务器不支持屏保扩展"));
+        }
+        let has_dpms = has_extension(dpms::X11_EXTENSION_NAME)?;
+        Ok(X11Backend {
+            connection,
+            has_dpms,
+            saved_dpms: None,
+            reason: None,
+        })
+    }
+
+    /// 读取当前 DPMS 设置
+    fn read_dpms(&self) -> io::Result<DpmsSettings> {
+        let info = self
+            .connection
+            .dpms_info()
+            .map_err(io::Error::other)?
+            .reply()
+            .map_err(io::Error::other)?;
+        let timeouts = self
+            .connection
+            .dpms_get_timeouts()
+            .map_err(io::Error::other)?
+            .reply()
+            .map_err(io::Error::other)?;
+        Ok(DpmsSettings {
+            enabled: info.state,
+            standby: timeouts.standby_timeout,
+            suspend: timeouts.suspend_timeout,
+            off: timeouts.off_timeout,
+        })
+    }
+
+    /// 恢复之前保存的 DPMS 设置
+    fn restore_dpms(&self, settings: &DpmsSettings) -> io::Result<()> {
+        self.connection
+            .dpms_set_timeouts(settings.standby, settings.suspend, settings.off)
+            .map_err(io::Error::other)?
+            .check()
+            .map_err(io::Error::other)?;
+        if settings.enabled {
+            self.connection
+                .dpms_enable()
+                .map_err(io::Error::other)?
+                .check()
+                .map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn suspend_screensaver(&self, suspend: bool) -> io::Result<()> {
+        self.connection
+            .screensaver_suspend(suspend.into())
+            .map_err(io::Error::other)?
+            .check()
+            .map_err(io::Error::other)
+    }
+}
+
+impl PowerBackend for X11Backend {
+    fn name(&self) -> String {
+        "X11 ScreenSaver/DPMS".to_string()
+    }
+
+    fn acquire(&mut self, mode: KeepMode, reason: &str) -> io::Result<()> {
+        if !mode.keeps_display() {
+            return Err(unsupported_mode(mode));
+        }
+        self.suspend_screensaver(true)?;
+        // 重复申请时保留最初的设置，避免把“已关闭”当成原始状态保存
+        if self.has_dpms && self.saved_dpms.is_none() {
+            self.saved_dpms = Some(self.read_dpms()?);
+            self.connection
+                .dpms_disable()
+                .map_err(io::Error::other)?
+                .check()
+                .map_err(io::Error::other)?;
+        }
+        self.reason = Some(reason.to_string());
+        Ok(())
+    }
+
+    /// 各步骤互不依赖，某一步失败时仍继续其余步骤；失败的步骤保留状态，再次释放时重试
+    fn release(&mut self) -> io::Result<()> {
+        if self.reason.is_none() {
+            return Ok(());
+        }
+        let dpms = match &self.saved_dpms {
+            Some(settings) => self.restore_dpms(settings),
+            None => Ok(()),
+        };
+        if dpms.is_ok() {
+            self.saved_dpms = None;
+        }
+        let screensaver = self.suspend_screensaver(false);
+        let flush = self.connection.flush().map_err(io::Error::other);
+        dpms.and(screensaver).and(flush)?;
+        self.reason = None;
+        Ok(())
+    }
+
+    fn query(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    /// 确认 DPMS 仍处于关闭状态，其他程序可能会重新打开它
+    fn verify(&self) -> Option<bool> {
+        self.saved_dpms.as_ref()?;
+        let info = self
+            .connection
+            .dpms_info()
+            .map_err(io::Error::other)
+            .and_then(|cookie| cookie.reply().map_err(io::Error::other));
+        match info {
+            Ok(info) => Some(!info.state),
+            Err(e) => {
+                eprintln!("读取 DPMS 状态失败: {}", e);
+                None
+            }
+        }
+    }
+
+    fn blocks_sleep(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testdisplay::Xvfb;
+
+    #[test]
+    #[ignore = "需要 Xvfb"]
+    fn disables_dpms_and_restores_timeouts() {
+        let xvfb = Xvfb::start();
+        let (observer, _) = x11rb::connect(Some(xvfb.display())).unwrap();
+        observer.dpms_set_timeouts(60, 120, 180).unwrap().check().unwrap();
+        observer.dpms_enable().unwrap().check().unwrap();
+
+        let mut backend = X11Backend::connect_to(Some(xvfb.display())).unwrap();
+        backend.acquire(KeepMode::DisplayOnly, "演示").unwrap();
+        assert!(!observer.dpms_info().unwrap().reply().unwrap().state);
+        assert_eq!(backend.verify(), Some(true));
+        observer.dpms_enable().unwrap().check().unwrap();
+        assert_eq!(backend.verify(), Some(false));
+
+        backend.release().unwrap();
+        assert!(observer.dpms_info().unwrap().reply().unwrap().state);
+        let timeouts = observer.dpms_get_timeouts().unwrap().reply().unwrap();
+        assert_eq!(
+            (timeouts.standby_timeout, timeouts.suspend_timeout, timeouts.off_timeout),
+            (60, 120, 180)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! 通过 XTest 扩展模拟输入
+
+use super::jiggler::InputInjector;
+use std::io;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xproto::MOTION_NOTIFY_EVENT;
+use x11rb::protocol::xtest::{self, ConnectionExt as _};
+use x11rb::rust_connection::RustConnection;
+use x11rb::NONE;
+
+/// 发送零距离的相对鼠标移动，X 服务器会因此重置空闲计时
+pub struct XTestInjector {
+    connection: RustConnection,
+}
+
+impl XTestInjector {
+    /// 连接 `DISPLAY` 指定的 X 服务器
+    pub fn connect() -> io::Result<Self> {
+        Self::connect_to(None)
+    }
+
+    /// 连接指定的 X 服务器，便于在测试中连接 Xvfb
+    pub fn connect_to(display: Option<&str>) -> io::Result<Self> {
+        let (connection, _) = x11rb::connect(display).map_err(io::Error::other)?;
+        if connection
+            .extension_information(xtest::X11_EXTENSION_NAME)
+            .map_err(io::Error::other)?
+            .is_none()
+        {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "X 服务器不支持 XTest 扩展"));
+        }
+        Ok(XTestInjector { connection })
+    }
+}
+
+impl InputInjector for XTestInjector {
+    fn inject(&mut self) -> io::Result<()> {
+        // detail 为 1 表示相对移动
+        self.connection
+            .xtest_fake_input(MOTION_NOTIFY_EVENT, 1, x11rb::CURRENT_TIME, NONE, 0, 0, 0)
+            .map_err(io::Error::other)?
+            .check()
+            .map_err(io::Error::other)?;
+        self.connection.flush().map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testdisplay::Xvfb;
+    use std::thread;
+    use std::time::Duration;
+    use x11rb::protocol::screensaver::ConnectionExt as _;
+
+    #[test]
+    #[ignore = "需要 Xvfb"]
+    fn resets_x_idle_counter() {
+        let xvfb = Xvfb::start();
+        let (observer, screen) = x11rb::connect(Some(xvfb.display())).unwrap();
+        let root = observer.setup().roots[screen].root;
+        let idle_ms = || {
+            observer
+                .screensaver_query_info(root)
+                .unwrap()
+                .reply()
+                .unwrap()
+                .ms_since_user_input
+        };
+
+        thread::sleep(Duration::from_millis(1500));
+        assert!(idle_ms() >= 1000);
+
+        let mut injector = XTestInjector::connect_to(Some(xvfb.display())).unwrap();
+        injector.inject().unwrap();
+        assert!(idle_ms() < 500);
+    }
+}
@@ -0,0 +1,1045 @@
+//! 纯粹的状态转换逻辑
+//!
+//! [`reduce`] 只根据当前状态和事件计算新状态，以及需要执行的副作用，
+//! 副作用由 [`controller`](super::controller) 负责执行。
+
+use super::clock::Now;
+use super::state::{AppState, Event, ExpiryAction, KeepMode, MissedExpiry, EXTEND_OPTIONS};
+use chrono::{DateTime, TimeDelta, Utc};
+use std::time::Duration;
+
+/// 到期事件晚到超过这么久时认为截止时间是在系统睡眠期间错过的
+const MISSED_THRESHOLD: Duration = Duration::from_secs(60);
+
+/// 处理事件后需要执行的副作用
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Effect {
+    /// 按指定模式申请保持唤醒，已持有时替换之前的申请
+    Acquire(KeepMode),
+    /// 释放保持唤醒
+    Release,
+    /// 启动到 `deadline` 结束的计时器，替换正在运行的计时器，到期时携带 `generation`
+    StartTimer { deadline: DateTime<Utc>, generation: u64 },
+    /// 停止计时器
+    StopTimer,
+    /// 关闭即将结束的通知
+    CloseNotification,
+    /// 重建托盘菜单
+    RefreshMenu,
+    /// 弹出托盘菜单
+    ShowMenu,
+    /// 用默认程序打开配置文件
+    OpenConfig,
+    /// 通知用户计时还剩多少分钟结束，并提供延长的操作
+    NotifyExpiry(u32),
+    /// 计时结束后执行锁屏、睡眠等操作
+    RunExpiryAction(ExpiryAction),
+    /// 在后台等待进程退出，退出后发送 [`Event::ProcessExited`]
+    WatchProcess(u32),
+    /// 读回后端状态，确认保持唤醒仍然生效
+    Verify,
+    /// 退出程序
+    Exit,
+}
+
+/// 根据事件计算新状态和副作用，不调用任何外部接口
+///
+/// `now` 是处理事件时的时间，用于计算计时结束的时间。
+pub fn reduce(state: &AppState, event: Event, now: Now) -> (AppState, Vec<Effect>) {
+    let mut next = state.clone();
+    let effects = match event {
+        Event::ToggleActive if state.is_active => deactivate(&mut next),
+        Event::ToggleActive => {
+            next.is_active = true;
+            let mut effects = vec![Effect::Acquire(next.mode)];
+            if let Some(seconds) = next.duration.seconds_from(&now.wall) {
+                effects.push(start_timer(&mut next, deadline_after(now, seconds)));
+            }
+            effects.push(Effect::RefreshMenu);
+            effects
+        }
+        // 只有当前计时器到期才关闭，手动切换之后旧计时器的事件会被忽略
+        Event::TimerExpired(generation) if state.is_active && generation == state.timer_generation => {
+            expire(&mut next, now)
+        }
+        Event::TimerExpired(_) => Vec::new(),
+        // 自动规则只影响是否持有保持唤醒，不改变手动开关和计时
+        Event::RuleChanged(index, matched) => match next.rules.get_mut(index) {
+            Some(rule) if rule.matched != matched => {
+                rule.matched = matched;
+                hold_changed(state, &mut next)
+            }
+            _ => Vec::new(),
+        },
+        // 等待进程同样只影响是否持有保持唤醒，再次选择同一个进程时取消等待
+        Event::ToggleProcessWait(pid) => match next.waiting_pids.iter().position(|&p| p == pid) {
+            Some(index) => {
+                next.waiting_pids.remove(index);
+                hold_changed(state, &mut next)
+            }
+            None => {
+                next.waiting_pids.push(pid);
+                let mut effects = vec![Effect::WatchProcess(pid)];
+                effects.extend(hold_changed(state, &mut next));
+                effects
+            }
+        },
+        Event::ProcessExited(pid) => match next.waiting_pids.iter().position(|&p| p == pid) {
+            Some(index) => {
+                next.waiting_pids.remove(index);
+                hold_changed(state, &mut next)
+            }
+            None => Vec::new(),
+        },
+        // 唤醒后按本地时间重新检查：已经过了截止时间就按策略处理，否则重新同步计时器
+        Event::Resumed => match state.deadline {
+            Some(deadline) if state.is_active && now.wall >= deadline => expire(&mut next, now),
+            Some(deadline) if state.is_active => vec![start_timer(&mut next, deadline), Effect::RefreshMenu],
+            _ => Vec::new(),
+        },
+        // 计时期间刷新菜单中的剩余时间，快结束时发出通知
+        Event::Tick => match state.remaining(&now.wall) {
+            Some(remaining) => {
+                let mut effects = vec![Effect::RefreshMenu];
+                // Tick 落在剩余整分钟处，四舍五入以免提前醒来时差一分钟
+                let minutes = ((remaining.as_secs() + 30) / 60) as u32;
+                if state.is_active && !state.expiry_warned && minutes > 0 && minutes <= state.warn_before_minutes {
+                    next.expiry_warned = true;
+                    effects.push(Effect::NotifyExpiry(minutes));
+                }
+                effects
+            }
+            None => Vec::new(),
+        },
+        Event::TogglePause => match state.paused {
+            // 带着冻结的剩余时间继续
+            Some(remaining) if state.is_active => {
+                next.paused = None;
+                let deadline = deadline_after(now, remaining.as_secs());
+                vec![Effect::Acquire(next.mode), start_timer(&mut next, deadline), Effect::RefreshMenu]
+            }
+            // 只有计时中的会话可以暂停，永久保持直接关闭即可
+            None => match state.remaining(&now.wall) {
+                Some(remaining) if state.is_active => {
+                    next.paused = Some(remaining);
+                    let mut effects = release_unless_held(&mut next);
+                    effects.extend([stop_timer(&mut next), Effect::RefreshMenu]);
+                    effects
+                }
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        },
+        // 暂停期间选择新的时长只替换冻结的剩余时间，选择永久则直接继续
+        Event::SetDuration(new_duration) if state.is_active && state.paused.is_some() => {
+            next.duration = new_duration;
+            match new_duration.seconds_from(&now.wall) {
+                Some(seconds) => {
+                    next.paused = Some(Duration::from_secs(seconds));
+                    vec![Effect::RefreshMenu]
+                }
+                None => {
+                    next.paused = None;
+                    vec![Effect::Acquire(next.mode), Effect::RefreshMenu]
+                }
+            }
+        }
+        Event::SetDuration(new_duration) => {
+            next.duration = new_duration;
+            let mut effects = Vec::new();
+            if next.is_active {
+                // 切换到永久时也要停掉之前的计时器
+                effects.push(match new_duration.seconds_from(&now.wall) {
+                    Some(seconds) => start_timer(&mut next, deadline_after(now, seconds)),
+                    None => stop_timer(&mut next),
+                });
+            }
+            effects.push(Effect::RefreshMenu);
+            effects
+        }
+        // 在当前截止时间上顺延，不从零开始重新计时；暂停时加到冻结的剩余时间上
+        Event::Extend(minutes) if state.is_active && state.paused.is_some() => {
+            next.paused = state.paused.map(|paused| paused + Duration::from_secs(minutes as u64 * 60));
+            vec![Effect::RefreshMenu]
+        }
+        Event::Extend(minutes) => match state.deadline {
+            Some(deadline) if state.is_active => {
+                let deadline = deadline.max(now.wall.to_utc()) + TimeDelta::minutes(minutes as i64);
+                vec![start_timer(&mut next, deadline), Effect::RefreshMenu]
+            }
+            _ => Vec::new(),
+        },
+        Event::SetMode(new_mode) => {
+            next.mode = new_mode;
+            if next.holds_keep_awake() {
+                vec![Effect::Acquire(new_mode), Effect::RefreshMenu]
+            } else {
+                vec![Effect::RefreshMenu]
+            }
+        }
+        Event::SetExpiryAction(action) => {
+            next.expiry_action = action;
+            vec![Effect::RefreshMenu]
+        }
+        // ThemeChanged 只需要更新菜单
+        Event::ThemeChanged => vec![Effect::RefreshMenu],
+        Event::Exit => {
+            next.warning = None;
+            vec![stop_timer(&mut next), Effect::Release, Effect::Exit]
+        }
+        // 只在持有时读回，其他程序或服务重启都可能让申请失效
+        Event::Verify if state.holds_keep_awake() => vec![Effect::Verify],
+        Event::Verify => Vec::new(),
+        Event::ShowMenu => vec![Effect::ShowMenu],
+        Event::OpenConfig => vec![Effect::OpenConfig],
+        Event::NoOp => Vec::new(),
+    };
+    // 计时变化后之前的通知已经过时；重新同步同一个截止时间时通知仍然有效
+    let effects = if state.expiry_warned && !next.expiry_warned {
+        std::iter::once(Effect::CloseNotification).chain(effects).collect()
+    } else {
+        effects
+    };
+    (next, effects)
+}
+
+/// 关闭保持亮屏，同时结束暂停
+fn deactivate(next: &mut AppState) -> Vec<Effect> {
+    next.is_active = false;
+    next.paused = None;
+    let mut effects = release_unless_held(next);
+    effects.extend([stop_timer(next), Effect::RefreshMenu]);
+    effects
+}
+
+/// 自动规则或等待的进程变化后，按是否持有保持唤醒的变化申请或释放，并刷新菜单
+fn hold_changed(state: &AppState, next: &mut AppState) -> Vec<Effect> {
+    let mut effects = match (state.holds_keep_awake(), next.holds_keep_awake()) {
+        (false, true) => vec![Effect::Acquire(next.mode)],
+        (true, false) => {
+            next.warning = None;
+            vec![Effect::Release]
+        }
+        _ => Vec::new(),
+    };
+    effects.push(Effect::RefreshMenu);
+    effects
+}
+
+/// 没有自动规则保持唤醒时释放
+fn release_unless_held(next: &mut AppState) -> Vec<Effect> {
+    if next.holds_keep_awake() {
+        return Vec::new();
+    }
+    next.warning = None;
+    vec![Effect::Release]
+}
+
+/// 计时到期：按时到达时执行计时结束的操作，在睡眠期间错过时按 [`MissedExpiry`] 处理
+fn expire(next: &mut AppState, now: Now) -> Vec<Effect> {
+    let missed = next.deadline.is_some_and(|deadline| now.since(deadline) > MISSED_THRESHOLD);
+    let run_action = match next.missed_expiry {
+        _ if !missed => true,
+        MissedExpiry::Expire => true,
+        MissedExpiry::Release => false,
+        MissedExpiry::Extend => {
+            let deadline = deadline_after(now, EXTEND_OPTIONS[0] as u64 * 60);
+            return vec![start_timer(next, deadline), Effect::RefreshMenu];
+        }
+    };
+    let action = next.expiry_action;
+    let mut effects = deactivate(next);
+    // 自动规则仍在保持唤醒时不锁屏或睡眠
+    if run_action && action != ExpiryAction::Release && !next.holds_keep_awake() {
+        effects.push(Effect::RunExpiryAction(action));
+    }
+    effects
+}
+
+/// 从 `now` 起经过 `seconds` 秒的截止时间
+fn deadline_after(now: Now, seconds: u64) -> DateTime<Utc> {
+    now.wall.to_utc() + TimeDelta::seconds(seconds as i64)
+}
+
+/// 进入新的计时器代数并启动计时器
+fn start_timer(next: &mut AppState, deadline: DateTime<Utc>) -> Effect {
+    next.timer_generation += 1;
+    // 只是重新同步同一个截止时间时，已经发出的通知仍然有效
+    if next.deadline != Some(deadline) {
+        next.expiry_warned = false;
+    }
+    next.deadline = Some(deadline);
+    Effect::StartTimer {
+        deadline,
+        generation: next.timer_generation,
+    }
+}
+
+/// 进入新的计时器代数，已经发出的到期事件都会失效
+fn stop_timer(next: &mut AppState) -> Effect {
+    next.timer_generation += 1;
+    next.deadline = None;
+    next.expiry_warned = false;
+    Effect::StopTimer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::state::{DurationOption, RuleStatus, Warning};
+    use crate::app::clock;
+    use chrono::NaiveTime;
+
+    fn active() -> AppState {
+        AppState::new()
+    }
+
+    fn inactive() -> AppState {
+        AppState {
+            is_active: false,
+            ..AppState::new()
+        }
+    }
+
+    #[test]
+    fn toggle_on_acquires_with_current_mode() {
+        let state = AppState {
+            mode: KeepMode::SystemOnly,
+            ..inactive()
+        };
+        let (next, effects) = reduce(&state, Event::ToggleActive, Now::current());
+        assert!(next.is_active);
+        assert_eq!(effects, vec![Effect::Acquire(KeepMode::SystemOnly), Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn toggle_on_starts_timer_for_finite_duration() {
+        let state = AppState {
+            duration: DurationOption::Minutes(15),
+            ..inactive()
+        };
+        let now = Now::current();
+        let (_, effects) = reduce(&state, Event::ToggleActive, now);
+        assert_eq!(
+            effects,
+            vec![
+                Effect::Acquire(KeepMode::DisplayAndSystem),
+                Effect::StartTimer {
+                    deadline: deadline_after(now, 15 * 60),
+                    generation: 1,
+                },
+                Effect::RefreshMenu,
+            ]
+        );
+    }
+
+    #[test]
+    fn toggle_off_releases_and_clears_warning() {
+        let state = AppState {
+            warning: Some(Warning::NotConfirmed),
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::ToggleActive, Now::current());
+        assert!(!next.is_active);
+        assert_eq!(next.warning, None);
+        assert_eq!(effects, vec![Effect::Release, Effect::StopTimer, Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn set_duration_restarts_timer_while_active() {
+        let now = Now::current();
+        let (next, effects) = reduce(&active(), Event::SetDuration(DurationOption::Minutes(30)), now);
+        assert_eq!(next.duration, DurationOption::Minutes(30));
+        assert_eq!(next.timer_generation, 1);
+        assert_eq!(
+            effects,
+            vec![
+                Effect::StartTimer {
+                    deadline: deadline_after(now, 30 * 60),
+                    generation: 1,
+                },
+                Effect::RefreshMenu,
+            ]
+        );
+    }
+
+    #[test]
+    fn set_duration_to_permanent_stops_timer() {
+        let state = AppState {
+            duration: DurationOption::Minutes(60),
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::SetDuration(DurationOption::Permanent), Now::current());
+        assert_eq!(next.duration, DurationOption::Permanent);
+        assert_eq!(effects, vec![Effect::StopTimer, Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn set_duration_while_inactive_only_updates_state() {
+        let (next, effects) = reduce(&inactive(), Event::SetDuration(DurationOption::Minutes(120)), Now::current());
+        assert_eq!(next.duration, DurationOption::Minutes(120));
+        assert!(!next.is_active);
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn extend_pushes_deadline_forward() {
+        let now = Now::current();
+        let state = AppState {
+            deadline: Some(deadline_after(now, 5 * 60)),
+            timer_generation: 2,
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::Extend(15), now);
+        assert_eq!(next.deadline, Some(deadline_after(now, 20 * 60)));
+        assert_eq!(next.duration, state.duration);
+        assert_eq!(
+            effects,
+            vec![
+                Effect::StartTimer {
+                    deadline: deadline_after(now, 20 * 60),
+                    generation: 3,
+                },
+                Effect::RefreshMenu,
+            ]
+        );
+    }
+
+    #[test]
+    fn pause_releases_and_freezes_remaining_time() {
+        let now = Now::current();
+        let state = AppState {
+            deadline: Some(deadline_after(now, 20 * 60)),
+            warning: Some(Warning::NotConfirmed),
+            timer_generation: 2,
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::TogglePause, now);
+        assert!(next.is_active);
+        assert!(!next.holds_keep_awake());
+        assert_eq!(next.paused, Some(Duration::from_secs(20 * 60)));
+        assert_eq!(next.deadline, None);
+        assert_eq!(next.warning, None);
+        assert_eq!(effects, vec![Effect::Release, Effect::StopTimer, Effect::RefreshMenu]);
+
+        // 暂停期间过去的时间不计入，继续时从冻结的剩余时间开始
+        let later = now.advanced(Duration::from_secs(3600));
+        let (next, effects) = reduce(&next, Event::TogglePause, later);
+        let deadline = deadline_after(later, 20 * 60);
+        assert!(next.holds_keep_awake());
+        assert_eq!(next.paused, None);
+        assert_eq!(next.deadline, Some(deadline));
+        assert_eq!(
+            effects,
+            vec![
+                Effect::Acquire(KeepMode::DisplayAndSystem),
+                Effect::StartTimer { deadline, generation: 4 },
+                Effect::RefreshMenu,
+            ]
+        );
+    }
+
+    #[test]
+    fn only_timed_sessions_can_pause() {
+        let (next, effects) = reduce(&active(), Event::TogglePause, Now::current());
+        assert_eq!(next.paused, None);
+        assert!(effects.is_empty());
+        let (_, effects) = reduce(&inactive(), Event::TogglePause, Now::current());
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn paused_session_adjusts_frozen_time() {
+        let state = AppState {
+            paused: Some(Duration::from_secs(5 * 60)),
+            ..active()
+        };
+        let now = Now::current();
+        let (next, effects) = reduce(&state, Event::Extend(15), now);
+        assert_eq!(next.paused, Some(Duration::from_secs(20 * 60)));
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+
+        let (next, effects) = reduce(&next, Event::SetDuration(DurationOption::Minutes(60)), now);
+        assert_eq!(next.paused, Some(Duration::from_secs(60 * 60)));
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+
+        // 暂停期间切换模式不会申请
+        let (next, effects) = reduce(&next, Event::SetMode(KeepMode::SystemOnly), now);
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+
+        // 选择永久保持时直接继续
+        let (next, effects) = reduce(&next, Event::SetDuration(DurationOption::Permanent), now);
+        assert!(next.holds_keep_awake());
+        assert_eq!(effects, vec![Effect::Acquire(KeepMode::SystemOnly), Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn turning_off_ends_pause() {
+        let state = AppState {
+            paused: Some(Duration::from_secs(5 * 60)),
+            ..active()
+        };
+        let (next, _) = reduce(&state, Event::ToggleActive, Now::current());
+        assert!(!next.is_active);
+        assert_eq!(next.paused, None);
+
+        // 重新开启时按选中的时长重新计时
+        let (next, _) = reduce(&next, Event::ToggleActive, Now::current());
+        assert!(next.holds_keep_awake());
+    }
+
+    #[test]
+    fn extend_without_timer_is_ignored() {
+        let (next, effects) = reduce(&active(), Event::Extend(60), Now::current());
+        assert_eq!(next.deadline, None);
+        assert!(effects.is_empty());
+
+        let state = AppState {
+            deadline: Some(Utc::now()),
+            ..inactive()
+        };
+        let (_, effects) = reduce(&state, Event::Extend(60), Now::current());
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn set_mode_reacquires_while_active() {
+        let (next, effects) = reduce(&active(), Event::SetMode(KeepMode::DisplayOnly), Now::current());
+        assert_eq!(next.mode, KeepMode::DisplayOnly);
+        assert_eq!(effects, vec![Effect::Acquire(KeepMode::DisplayOnly), Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn set_mode_while_inactive_only_updates_state() {
+        let (next, effects) = reduce(&inactive(), Event::SetMode(KeepMode::SystemOnly), Now::current());
+        assert_eq!(next.mode, KeepMode::SystemOnly);
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn theme_changed_only_refreshes_menu() {
+        let state = active();
+        let (next, effects) = reduce(&state, Event::ThemeChanged, Now::current());
+        assert_eq!(next.is_active, state.is_active);
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn exit_stops_timer_then_releases() {
+        let state = AppState {
+            warning: Some(Warning::AcquireFailed("测试".to_string())),
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::Exit, Now::current());
+        assert_eq!(next.warning, None);
+        assert_eq!(effects, vec![Effect::StopTimer, Effect::Release, Effect::Exit]);
+    }
+
+    #[test]
+    fn show_menu_has_no_state_change() {
+        let (next, effects) = reduce(&active(), Event::ShowMenu, Now::current());
+        assert!(next.is_active);
+        assert_eq!(effects, vec![Effect::ShowMenu]);
+    }
+
+    #[test]
+    fn open_config_has_no_state_change() {
+        let (next, effects) = reduce(&inactive(), Event::OpenConfig, Now::current());
+        assert!(!next.is_active);
+        assert_eq!(effects, vec![Effect::OpenConfig]);
+    }
+
+    #[test]
+    fn verify_only_while_holding() {
+        let (_, effects) = reduce(&active(), Event::Verify, Now::current());
+        assert_eq!(effects, vec![Effect::Verify]);
+        let (_, effects) = reduce(&inactive(), Event::Verify, Now::current());
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn noop_has_no_effects() {
+        let state = inactive();
+        let (next, effects) = reduce(&state, Event::NoOp, Now::current());
+        assert_eq!(next.is_active, state.is_active);
+        assert_eq!(next.duration, state.duration);
+        assert_eq!(next.mode, state.mode);
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn reduce_does_not_modify_input() {
+        let state = active();
+        let _ = reduce(&state, Event::ToggleActive, Now::current());
+        assert!(state.is_active);
+    }
+
+    #[test]
+    fn current_timer_expiry_deactivates() {
+        let state = AppState {
+            timer_generation: 3,
+            warning: Some(Warning::NotConfirmed),
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::TimerExpired(3), Now::current());
+        assert!(!next.is_active);
+        assert_eq!(next.warning, None);
+        assert_eq!(next.timer_generation, 4);
+        assert_eq!(effects, vec![Effect::Release, Effect::StopTimer, Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn expiry_runs_selected_action_after_release() {
+        let state = AppState {
+            timer_generation: 3,
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::SetExpiryAction(ExpiryAction::Lock), Now::current());
+        assert_eq!(next.expiry_action, ExpiryAction::Lock);
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+
+        let (next, effects) = reduce(&next, Event::TimerExpired(3), Now::current());
+        assert!(!next.is_active);
+        assert_eq!(next.expiry_action, ExpiryAction::Lock);
+        assert_eq!(
+            effects,
+            vec![
+                Effect::Release,
+                Effect::StopTimer,
+                Effect::RefreshMenu,
+                Effect::RunExpiryAction(ExpiryAction::Lock),
+            ]
+        );
+    }
+
+    #[test]
+    fn manual_stop_does_not_run_expiry_action() {
+        let state = AppState {
+            expiry_action: ExpiryAction::Suspend,
+            ..active()
+        };
+        let (_, effects) = reduce(&state, Event::ToggleActive, Now::current());
+        assert!(!effects.iter().any(|e| matches!(e, Effect::RunExpiryAction(_))));
+
+        // 过时的到期事件同样不会触发
+        let (_, effects) = reduce(&state, Event::TimerExpired(state.timer_generation + 1), Now::current());
+        assert!(effects.is_empty());
+    }
+
+    /// 计时 30 分钟后系统睡眠了 2 小时，唤醒时的状态和时间
+    fn slept_through_deadline(missed_expiry: MissedExpiry) -> (AppState, Now) {
+        let now = Now::current();
+        let state = AppState {
+            deadline: Some(deadline_after(now, 30 * 60)),
+            timer_generation: 3,
+            expiry_action: ExpiryAction::Suspend,
+            missed_expiry,
+            ..active()
+        };
+        (state, now.slept(Duration::from_secs(2 * 3600)))
+    }
+
+    #[test]
+    fn missed_expiry_releases_without_action_by_default() {
+        let (state, now) = slept_through_deadline(MissedExpiry::Release);
+        let (next, effects) = reduce(&state, Event::Resumed, now);
+        assert!(!next.is_active);
+        assert_eq!(effects, vec![Effect::Release, Effect::StopTimer, Effect::RefreshMenu]);
+
+        // 计时器线程先发现到期时同样处理
+        let (_, effects) = reduce(&state, Event::TimerExpired(3), now);
+        assert_eq!(effects, vec![Effect::Release, Effect::StopTimer, Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn missed_expiry_can_run_action() {
+        let (state, now) = slept_through_deadline(MissedExpiry::Expire);
+        let (_, effects) = reduce(&state, Event::Resumed, now);
+        assert_eq!(effects.last(), Some(&Effect::RunExpiryAction(ExpiryAction::Suspend)));
+    }
+
+    #[test]
+    fn missed_expiry_can_extend_from_wake_up() {
+        let (state, now) = slept_through_deadline(MissedExpiry::Extend);
+        let (next, effects) = reduce(&state, Event::Resumed, now);
+        assert!(next.is_active);
+        let deadline = deadline_after(now, EXTEND_OPTIONS[0] as u64 * 60);
+        assert_eq!(next.deadline, Some(deadline));
+        assert_eq!(effects, vec![Effect::StartTimer { deadline, generation: 4 }, Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn on_time_expiry_ignores_missed_policy() {
+        let (state, _) = slept_through_deadline(MissedExpiry::Release);
+        let now = Now::current().advanced(Duration::from_secs(30 * 60));
+        let (_, effects) = reduce(&state, Event::TimerExpired(3), now);
+        assert_eq!(effects.last(), Some(&Effect::RunExpiryAction(ExpiryAction::Suspend)));
+    }
+
+    #[test]
+    fn resume_before_deadline_resyncs_timer() {
+        let now = Now::current();
+        let deadline = deadline_after(now, 2 * 3600);
+        let state = AppState {
+            deadline: Some(deadline),
+            expiry_warned: true,
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::Resumed, now.slept(Duration::from_secs(3600)));
+        assert!(next.is_active);
+        assert!(next.expiry_warned);
+        assert_eq!(effects, vec![Effect::StartTimer { deadline, generation: 1 }, Effect::RefreshMenu]);
+
+        // 永久保持时没有需要检查的计时
+        let (_, effects) = reduce(&active(), Event::Resumed, now);
+        assert!(effects.is_empty());
+    }
+
+    fn with_rule(state: AppState) -> AppState {
+        AppState {
+            rules: vec![RuleStatus {
+                label: "ffmpeg".to_string(),
+                matched: false,
+            }],
+            ..state
+        }
+    }
+
+    #[test]
+    fn rule_holds_while_manually_off() {
+        let state = with_rule(inactive());
+        let (next, effects) = reduce(&state, Event::RuleChanged(0, true), Now::current());
+        assert!(!next.is_active);
+        assert!(next.holds_keep_awake());
+        assert_eq!(effects, vec![Effect::Acquire(KeepMode::DisplayAndSystem), Effect::RefreshMenu]);
+
+        // 重复的结果和不存在的规则都不产生副作用
+        let (_, effects) = reduce(&next, Event::RuleChanged(0, true), Now::current());
+        assert!(effects.is_empty());
+        let (_, effects) = reduce(&next, Event::RuleChanged(3, false), Now::current());
+        assert!(effects.is_empty());
+
+        let (next, effects) = reduce(&next, Event::RuleChanged(0, false), Now::current());
+        assert!(!next.holds_keep_awake());
+        assert_eq!(effects, vec![Effect::Release, Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn rule_keeps_holding_after_manual_off() {
+        let state = with_rule(active());
+        let (next, effects) = reduce(&state, Event::RuleChanged(0, true), Now::current());
+        // 已经持有，不重复申请
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+
+        let (next, effects) = reduce(&next, Event::ToggleActive, Now::current());
+        assert!(!next.is_active);
+        assert_eq!(effects, vec![Effect::StopTimer, Effect::RefreshMenu]);
+
+        // 手动开启期间规则结束时继续保持
+        let (next, _) = reduce(&next, Event::ToggleActive, Now::current());
+        let (_, effects) = reduce(&next, Event::RuleChanged(0, false), Now::current());
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn expiry_action_waits_for_rules() {
+        let mut state = with_rule(AppState {
+            timer_generation: 3,
+            expiry_action: ExpiryAction::Suspend,
+            ..active()
+        });
+        state.rules[0].matched = true;
+        let (next, effects) = reduce(&state, Event::TimerExpired(3), Now::current());
+        assert!(!next.is_active);
+        assert!(next.holds_keep_awake());
+        assert_eq!(effects, vec![Effect::StopTimer, Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn waits_for_process_while_manually_off() {
+        let (next, effects) = reduce(&inactive(), Event::ToggleProcessWait(42), Now::current());
+        assert_eq!(next.waiting_pids, vec![42]);
+        assert_eq!(
+            effects,
+            vec![Effect::WatchProcess(42), Effect::Acquire(KeepMode::DisplayAndSystem), Effect::RefreshMenu]
+        );
+
+        let (next, _) = reduce(&next, Event::ToggleProcessWait(7), Now::current());
+        let (next, effects) = reduce(&next, Event::ProcessExited(42), Now::current());
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+
+        // 取消等待后不再持有，之后才收到的退出事件被忽略
+        let (next, effects) = reduce(&next, Event::ToggleProcessWait(7), Now::current());
+        assert!(next.waiting_pids.is_empty());
+        assert_eq!(effects, vec![Effect::Release, Effect::RefreshMenu]);
+        let (_, effects) = reduce(&next, Event::ProcessExited(7), Now::current());
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn stale_timer_expiry_is_ignored() {
+        let state = AppState {
+            timer_generation: 3,
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::TimerExpired(2), Now::current());
+        assert!(next.is_active);
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn timer_expiry_never_activates() {
+        let state = AppState {
+            timer_generation: 3,
+            ..inactive()
+        };
+        let (next, effects) = reduce(&state, Event::TimerExpired(3), Now::current());
+        assert!(!next.is_active);
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn timer_tracks_deadline() {
+        let now = Now::current();
+        let state = AppState {
+            duration: DurationOption::Minutes(30),
+            ..inactive()
+        };
+        let (next, _) = reduce(&state, Event::ToggleActive, now);
+        assert_eq!(next.deadline, Some(deadline_after(now, 30 * 60)));
+        assert_eq!(
+            next.remaining(&now.advanced(Duration::from_secs(60)).wall),
+            Some(Duration::from_secs(29 * 60))
+        );
+
+        let (next, _) = reduce(&next, Event::SetDuration(DurationOption::Permanent), now);
+        assert_eq!(next.deadline, None);
+        assert_eq!(next.remaining(&now.wall), None);
+    }
+
+    #[test]
+    fn tick_refreshes_menu_only_while_timed() {
+        let (_, effects) = reduce(&active(), Event::Tick, Now::current());
+        assert!(effects.is_empty());
+
+        let now = Now::current();
+        let state = AppState {
+            deadline: Some(deadline_after(now, 30 * 60)),
+            ..active()
+        };
+        let (_, effects) = reduce(&state, Event::Tick, now);
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn tick_notifies_once_before_expiry() {
+        let now = Now::current();
+        let state = AppState {
+            deadline: Some(deadline_after(now, 5 * 60)),
+            warn_before_minutes: 5,
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::Tick, now);
+        assert!(next.expiry_warned);
+        assert_eq!(effects, vec![Effect::RefreshMenu, Effect::NotifyExpiry(5)]);
+
+        let (_, effects) = reduce(&next, Event::Tick, now.advanced(Duration::from_secs(60)));
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn notification_closes_only_when_deadline_changes() {
+        let now = Now::current();
+        let deadline = deadline_after(now, 3 * 60);
+        let state = AppState {
+            deadline: Some(deadline),
+            expiry_warned: true,
+            ..active()
+        };
+        let (next, effects) = reduce(&state, Event::Resumed, now);
+        assert!(next.expiry_warned);
+        assert!(!effects.contains(&Effect::CloseNotification));
+
+        let (next, effects) = reduce(&state, Event::Extend(15), now);
+        assert!(!next.expiry_warned);
+        assert_eq!(effects.first(), Some(&Effect::CloseNotification));
+
+        let (next, effects) = reduce(&state, Event::ToggleActive, now);
+        assert!(!next.expiry_warned);
+        assert_eq!(effects.first(), Some(&Effect::CloseNotification));
+    }
+
+    #[test]
+    fn notification_can_be_disabled() {
+        let now = Now::current();
+        let state = AppState {
+            deadline: Some(deadline_after(now, 60)),
+            warn_before_minutes: 0,
+            ..active()
+        };
+        let (_, effects) = reduce(&state, Event::Tick, now);
+        assert_eq!(effects, vec![Effect::RefreshMenu]);
+    }
+
+    #[test]
+    fn until_deadline_follows_wall_clock() {
+        let now = Now::current();
+        let target = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+        let state = AppState {
+            duration: DurationOption::Until(target),
+            ..inactive()
+        };
+        let (next, effects) = reduce(&state, Event::ToggleActive, now);
+        let seconds = clock::seconds_until(&now.wall, target);
+        let deadline = deadline_after(now, seconds);
+        assert!(effects.contains(&Effect::StartTimer { deadline, generation: 1 }));
+        assert_eq!(next.deadline, Some(deadline_after(now, seconds)));
+    }
+
+    /// 按虚拟时间执行计时器副作用的测试环境
+    ///
+    /// 到期事件先进入队列，再由 [`Harness::deliver`] 处理，
+    /// 可以模拟计时器线程已经发出事件、但用户的操作先被处理的情况。
+    struct Harness {
+        state: AppState,
+        start: Now,
+        now: u64,
+        timer: Option<(u64, u64)>,
+        queue: Vec<Event>,
+    }
+
+    impl Harness {
+        fn new(state: AppState) -> Self {
+            Harness {
+                state,
+                start: Now::current(),
+                now: 0,
+                timer: None,
+                queue: Vec::new(),
+            }
+        }
+
+        fn dispatch(&mut self, event: Event) {
+            let (next, effects) = reduce(&self.state, event, self.start.advanced(Duration::from_secs(self.now)));
+            self.state = next;
+            for effect in effects {
+                match effect {
+                    Effect::StartTimer { deadline, generation } => {
+                        let seconds = deadline.signed_duration_since(self.start.wall).num_seconds() as u64;
+                        self.timer = Some((seconds, generation))
+                    }
+                    Effect::StopTimer => self.timer = None,
+                    _ => {}
+                }
+            }
+        }
+
+        /// 推进虚拟时间，到期的计时器把事件放入队列
+        fn advance(&mut self, seconds: u64) {
+            self.now += seconds;
+            if let Some((deadline, generation)) = self.timer
+                && deadline <= self.now
+            {
+                self.timer = None;
+                self.queue.push(Event::TimerExpired(generation));
+            }
+        }
+
+        /// 处理队列中积压的事件
+        fn deliver(&mut self) {
+            for event in std::mem::take(&mut self.queue) {
+                self.dispatch(event);
+            }
+        }
+    }
+
+    #[test]
+    fn expiry_racing_with_manual_toggle_does_not_reactivate() {
+        let mut harness = Harness::new(AppState {
+            duration: DurationOption::Minutes(15),
+            ..inactive()
+        });
+        harness.dispatch(Event::ToggleActive);
+        harness.advance(15 * 60);
+
+        // 到期事件已经发出，但用户恰好在此时手动关闭
+        harness.dispatch(Event::ToggleActive);
+        harness.deliver();
+        assert!(!harness.state.is_active);
+    }
+
+    #[test]
+    fn expiry_racing_with_reactivation_keeps_new_session() {
+        let mut harness = Harness::new(AppState {
+            duration: DurationOption::Minutes(15),
+            ..inactive()
+        });
+        harness.dispatch(Event::ToggleActive);
+        harness.advance(15 * 60);
+
+        // 用户关闭后又立即打开，旧计时器的事件不能关掉新的会话
+        harness.dispatch(Event::ToggleActive);
+        harness.dispatch(Event::ToggleActive);
+        harness.deliver();
+        assert!(harness.state.is_active);
+
+        // 新的计时器照常到期
+        harness.advance(15 * 60);
+        harness.deliver();
+        assert!(!harness.state.is_active);
+    }
+
+    #[test]
+    fn expiry_after_duration_change_is_ignored() {
+        let mut harness = Harness::new(AppState {
+            duration: DurationOption::Minutes(15),
+            ..inactive()
+        });
+        harness.dispatch(Event::ToggleActive);
+        harness.advance(15 * 60);
+
+        harness.dispatch(Event::SetDuration(DurationOption::Minutes(60)));
+        harness.deliver();
+        assert!(harness.state.is_active);
+
+        harness.advance(45 * 60);
+        harness.deliver();
+        assert!(harness.state.is_active);
+        harness.advance(15 * 60);
+        harness.deliver();
+        assert!(!harness.state.is_active);
+    }
+
+    #[test]
+    fn extending_after_notification_rearms_it() {
+        let now = Now::current();
+        let state = AppState {
+            deadline: Some(deadline_after(now, 2 * 60)),
+            expiry_warned: true,
+            ..active()
+        };
+        let (next, _) = reduce(&state, Event::Extend(15), now);
+        assert!(!next.expiry_warned);
+
+        let (_, effects) = reduce(&next, Event::Tick, now.advanced(Duration::from_secs(12 * 60)));
+        assert_eq!(effects, vec![Effect::RefreshMenu, Effect::NotifyExpiry(5)]);
+    }
+
+    #[test]
+    fn extend_racing_with_expiry_keeps_session() {
+        let mut harness = Harness::new(AppState {
+            duration: DurationOption::Minutes(15),
+            ..inactive()
+        });
+        harness.dispatch(Event::ToggleActive);
+        harness.advance(10 * 60);
+        harness.dispatch(Event::Extend(15));
+        harness.advance(5 * 60);
+        harness.deliver();
+        assert!(harness.state.is_active);
+
+        harness.advance(15 * 60);
+        harness.deliver();
+        assert!(!harness.state.is_active);
+    }
+}
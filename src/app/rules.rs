@@ -0,0 +1,148 @@
+//! 自动保持唤醒的规则
+//!
+//! 每条规则都实现 [`Rule`]，由 [`watch`] 在后台线程中定期检查。
+//! 任意一条规则满足时，即使手动关闭了保持亮屏也会保持唤醒；都不满足时自动释放。
+
+mod cpu;
+mod network;
+mod process;
+
+pub use cpu::{CpuConfig, CpuRule};
+#[cfg(windows)]
+pub use cpu::CpuTimes;
+pub use network::{NetworkConfig, NetworkRule};
+pub use process::{list_processes, ProcessInfo, ProcessRule};
+
+use super::i18n::Translations;
+use super::state::Event;
+use crossbeam_channel::Sender;
+use serde::Deserialize;
+use std::cell::OnceCell;
+use std::thread;
+use std::time::Duration;
+
+/// 检查规则的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 保持唤醒的条件
+pub trait Rule: Send {
+    /// 显示在菜单中的说明
+    fn label(&self, t: &Translations) -> String;
+
+    /// 当前是否满足，每次检查调用一次
+    fn poll(&mut self, snapshot: &Snapshot) -> bool;
+
+    /// 是否需要进程的完整路径，只要有一条规则需要，枚举进程时才查询路径
+    fn needs_process_paths(&self) -> bool {
+        false
+    }
+}
+
+/// 一次检查中各条规则共享的系统状态，第一次用到时才读取，每次检查最多读取一次
+pub struct Snapshot {
+    with_paths: bool,
+    processes: OnceCell<Option<Vec<ProcessInfo>>>,
+}
+
+impl Snapshot {
+    /// `with_paths` 为 false 时 [`ProcessInfo::path`] 可能为 `None`
+    pub fn new(with_paths: bool) -> Self {
+        Snapshot {
+            with_paths,
+            processes: OnceCell::new(),
+        }
+    }
+
+    /// 正在运行的进程，枚举失败时为 `None`
+    pub fn processes(&self) -> Option<&[ProcessInfo]> {
+        self.processes
+            .get_or_init(|| match list_processes(self.with_paths) {
+                Ok(processes) => Some(processes),
+                Err(e) => {
+                    eprintln!("枚举进程失败: {}", e);
+                    None
+                }
+            })
+            .as_deref()
+    }
+}
+
+/// 配置文件中的规则
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleConfig {
+    /// 有名称或路径匹配的进程在运行，支持 `*` 和 `?` 通配符
+    Process(String),
+    /// 网络收发速率持续高于阈值
+    Network(NetworkConfig),
+    /// CPU 平均使用率持续高于阈值
+    Cpu(CpuConfig),
+}
+
+/// 按配置创建规则
+pub fn build(config: &RuleConfig) -> Box<dyn Rule> {
+    match config {
+        RuleConfig::Process(pattern) => Box::new(ProcessRule::new(pattern)),
+        RuleConfig::Network(config) => Box::new(NetworkRule::new(config)),
+        RuleConfig::Cpu(config) => Box::new(CpuRule::new(config)),
+    }
+}
+
+/// 在后台线程中定期检查规则，结果变化时发送 [`Event::RuleChanged`]
+pub fn watch(mut rules: Vec<Box<dyn Rule>>, event_tx: Sender<Event>) {
+    if rules.is_empty() {
+        return;
+    }
+    let with_paths = rules.iter().any(|rule| rule.needs_process_paths());
+    thread::spawn(move || {
+        let mut matched = vec![false; rules.len()];
+        loop {
+            let snapshot = Snapshot::new(with_paths);
+            for (index, rule) in rules.iter_mut().enumerate() {
+                let now = rule.poll(&snapshot);
+                if now != matched[index] {
+                    matched[index] = now;
+                    if event_tx.send(Event::RuleChanged(index, now)).is_err() {
+                        return;
+                    }
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn snapshot_enumerates_processes_once() {
+        let snapshot = Snapshot::new(false);
+        let first = snapshot.processes().unwrap();
+        assert!(first.iter().any(|process| process.pid == std::process::id()));
+        assert!(std::ptr::eq(first, snapshot.processes().unwrap()));
+    }
+
+    #[test]
+    fn rules_are_read_from_config() {
+        let rules: Vec<RuleConfig> = serde_json::from_str(r#"[{"process": "ffmpeg"}, {"process": "/usr/bin/rsync"}, {"network": {"min_kib_per_sec": 500}}, {"cpu": {"min_percent": 80}}]"#).unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                RuleConfig::Process("ffmpeg".to_string()),
+                RuleConfig::Process("/usr/bin/rsync".to_string()),
+                RuleConfig::Network(NetworkConfig {
+                    min_kib_per_sec: 500,
+                    grace_secs: 60,
+                }),
+                RuleConfig::Cpu(CpuConfig {
+                    min_percent: 80,
+                    average_secs: 60,
+                    idle_minutes: 5,
+                }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! CPU 规则：CPU 持续繁忙时保持唤醒
+//!
+//! Linux 上读取 `/proc/stat` 的总计行，Windows 上使用 `GetSystemTimes`，
+//! 按一段时间内非空闲时间的占比计算平均使用率。
+
+use super::{Rule, Snapshot};
+use crate::app::i18n::Translations;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// 配置文件中的 CPU 规则
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct CpuConfig {
+    /// 平均使用率达到多少百分比时开始保持
+    pub min_percent: u32,
+    /// 按最近多少秒计算平均使用率
+    pub average_secs: u64,
+    /// 平均使用率低于阈值持续多少分钟后释放
+    pub idle_minutes: u64,
+}
+
+impl Default for CpuConfig {
+    fn default() -> Self {
+        CpuConfig {
+            min_percent: 50,
+            average_secs: 60,
+            idle_minutes: 5,
+        }
+    }
+}
+
+/// 自开机以来所有 CPU 累计的时间，单位由平台决定，只用于计算占比
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct CpuTimes {
+    pub idle: u64,
+    pub total: u64,
+}
+
+/// 最近 `average_secs` 秒的平均使用率达到阈值时满足，之后空闲 `idle_minutes` 分钟才不再满足
+pub struct CpuRule {
+    config: CpuConfig,
+    /// 最近的采样，最早的一个恰好覆盖到平均窗口的起点
+    samples: VecDeque<(Instant, CpuTimes)>,
+    matched: bool,
+    /// 满足期间平均使用率从什么时候起低于阈值
+    idle_since: Option<Instant>,
+}
+
+impl CpuRule {
+    pub fn new(config: &CpuConfig) -> Self {
+        CpuRule {
+            config: config.clone(),
+            samples: VecDeque::new(),
+            matched: false,
+            idle_since: None,
+        }
+    }
+
+    /// 加入新的采样，返回是否满足
+    fn update(&mut self, now: Instant, times: CpuTimes) -> bool {
+        self.samples.push_back((now, times));
+        let window = Duration::from_secs(self.config.average_secs);
+        // 保留窗口起点之前的最后一个采样，去掉更早的
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= window {
+            self.samples.pop_front();
+        }
+        let (oldest_time, oldest) = self.samples[0];
+        // 采样还不够一个窗口时维持原状，避免刚启动时的短暂峰值
+        if now.duration_since(oldest_time) < window || times.total <= oldest.total {
+            return self.matched;
+        }
+        let total = times.total - oldest.total;
+        let busy = total.saturating_sub(times.idle.saturating_sub(oldest.idle));
+        let percent = busy as f64 * 100.0 / total as f64;
+
+        if percent >= self.config.min_percent as f64 {
+            self.matched = true;
+            self.idle_since = None;
+        } else if self.matched {
+            let since = *self.idle_since.get_or_insert(now);
+            if now.duration_since(since) >= Duration::from_secs(self.config.idle_minutes * 60) {
+                self.matched = false;
+                self.idle_since = None;
+            }
+        }
+        self.matched
+    }
+}
+
+impl Rule for CpuRule {
+    fn label(&self, t: &Translations) -> String {
+        t.get("rule_cpu").replace("{}", &self.config.min_percent.to_string())
+    }
+
+    fn poll(&mut self, _snapshot: &Snapshot) -> bool {
+        match cpu_times() {
+            Ok(times) => self.update(Instant::now(), times),
+            // 偶尔读取失败时维持原状，不中断正在进行的保持和空闲计时
+            Err(e) => {
+                eprintln!("读取 CPU 使用率失败: {}", e);
+                self.matched
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_times() -> io::Result<CpuTimes> {
+    parse_proc_stat(&std::fs::read_to_string("/proc/stat")?)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "无法解析 /proc/stat"))
+}
+
+#[cfg(windows)]
+fn cpu_times() -> io::Result<CpuTimes> {
+    crate::app::win_api::cpu_times()
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn cpu_times() -> io::Result<CpuTimes> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "当前平台不支持读取 CPU 使用率"))
+}
+
+/// 解析 `/proc/stat` 的 `cpu` 总计行
+///
+/// 依次是 user nice system idle iowait irq softirq steal guest guest_nice，
+/// guest 已经计入 user，只取前 8 项；iowait 算作空闲。
+#[cfg(any(target_os = "linux", test))]
+fn parse_proc_stat(content: &str) -> Option<CpuTimes> {
+    let line = content.lines().find(|line| line.starts_with("cpu "))?;
+    let fields: Vec<u64> = line.split_whitespace().skip(1).take(8).map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    if fields.len() < 5 {
+        return None;
+    }
+    Some(CpuTimes {
+        idle: fields[3] + fields[4],
+        total: fields.iter().sum(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proc_stat_counts_iowait_as_idle() {
+        let content = "cpu  100 5 50 800 20 3 2 0 40 0\ncpu0 50 2 25 400 10 1 1 0 20 0\nintr 12345\n";
+        assert_eq!(parse_proc_stat(content), Some(CpuTimes { idle: 820, total: 980 }));
+        assert_eq!(parse_proc_stat("intr 12345\n"), None);
+    }
+
+    #[test]
+    fn holds_while_average_is_high_and_releases_after_idle() {
+        let mut rule = CpuRule::new(&CpuConfig {
+            min_percent: 50,
+            average_secs: 20,
+            idle_minutes: 1,
+        });
+        let start = Instant::now();
+        let mut times = CpuTimes { idle: 0, total: 0 };
+        assert!(!rule.update(start, times));
+        // 每 5 秒检查一次，`percent` 是这段时间的使用率
+        let mut step = |rule: &mut CpuRule, n: u64, percent: u64| {
+            times.total += 500;
+            times.idle += 5 * (100 - percent);
+            rule.update(start + Duration::from_secs(n * 5), times)
+        };
+        // 不满一个窗口时不判断
+        for n in 1..4 {
+            assert!(!step(&mut rule, n, 100));
+        }
+        assert!(step(&mut rule, 4, 100));
+        // 短暂的空闲被窗口平均掉
+        assert!(step(&mut rule, 5, 0));
+        assert!(step(&mut rule, 6, 0));
+        // 平均值在第 7 次低于阈值，之后还要再空闲一分钟
+        for n in 7..19 {
+            assert!(step(&mut rule, n, 0));
+        }
+        assert!(!step(&mut rule, 19, 0));
+    }
+}
@@ -0,0 +1,174 @@
+//! 网络规则：持续有大流量传输时保持唤醒
+//!
+//! Linux 上读取 `/proc/net/dev`，Windows 上读取硬件网卡的 `GetIfTable2` 计数，
+//! 按两次检查之间收发字节数之和计算速率。
+
+use super::{Rule, Snapshot};
+use crate::app::i18n::Translations;
+use serde::Deserialize;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// 配置文件中的网络规则
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// 收发速率之和达到多少 KiB/s（1024 字节每秒）时开始保持
+    pub min_kib_per_sec: u64,
+    /// 速率降到阈值的一半以下后，再等多少秒才释放
+    pub grace_secs: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            min_kib_per_sec: 100,
+            grace_secs: 60,
+        }
+    }
+}
+
+/// 收发速率达到阈值时满足
+///
+/// 满足之后速率只要不低于阈值的一半就继续满足，短暂的波动不会释放；
+/// 低于一半并持续 `grace_secs` 后才不再满足。
+pub struct NetworkRule {
+    config: NetworkConfig,
+    /// 上次检查的时间和累计字节数
+    last: Option<(Instant, u64)>,
+    matched: bool,
+    /// 满足期间速率从什么时候起低于阈值的一半
+    below_since: Option<Instant>,
+}
+
+impl NetworkRule {
+    pub fn new(config: &NetworkConfig) -> Self {
+        NetworkRule {
+            config: config.clone(),
+            last: None,
+            matched: false,
+            below_since: None,
+        }
+    }
+
+    /// 按新的累计字节数更新状态，返回是否满足
+    fn update(&mut self, now: Instant, bytes: u64) -> bool {
+        let Some((last_time, last_bytes)) = self.last.replace((now, bytes)) else {
+            return self.matched;
+        };
+        let elapsed = now.duration_since(last_time).as_secs_f64();
+        if elapsed <= 0.0 {
+            return self.matched;
+        }
+        // 网卡重置时计数会变小，按没有流量处理
+        let rate = bytes.saturating_sub(last_bytes) as f64 / elapsed;
+        let threshold = self.config.min_kib_per_sec as f64 * 1024.0;
+        if rate >= threshold || (self.matched && rate >= threshold / 2.0) {
+            self.matched = true;
+            self.below_since = None;
+        } else if self.matched {
+            let since = *self.below_since.get_or_insert(now);
+            if now.duration_since(since) >= Duration::from_secs(self.config.grace_secs) {
+                self.matched = false;
+                self.below_since = None;
+            }
+        }
+        self.matched
+    }
+}
+
+impl Rule for NetworkRule {
+    fn label(&self, t: &Translations) -> String {
+        t.get("rule_network").replace("{}", &self.config.min_kib_per_sec.to_string())
+    }
+
+    fn poll(&mut self, _snapshot: &Snapshot) -> bool {
+        match total_bytes() {
+            Ok(bytes) => self.update(Instant::now(), bytes),
+            // 偶尔读取失败时维持原状，不中断正在进行的保持和宽限期
+            Err(e) => {
+                eprintln!("读取网络流量失败: {}", e);
+                self.matched
+            }
+        }
+    }
+}
+
+/// 除回环接口外所有网卡收发字节数之和
+#[cfg(target_os = "linux")]
+fn total_bytes() -> io::Result<u64> {
+    Ok(parse_net_dev(&std::fs::read_to_string("/proc/net/dev")?))
+}
+
+/// 硬件网卡收发字节数之和
+#[cfg(windows)]
+fn total_bytes() -> io::Result<u64> {
+    crate::app::win_api::network_bytes()
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn total_bytes() -> io::Result<u64> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "当前平台不支持读取网络流量"))
+}
+
+/// 解析 `/proc/net/dev`，前两行是表头，每行的第 1 和第 9 列是接收和发送的字节数
+#[cfg(any(target_os = "linux", test))]
+fn parse_net_dev(content: &str) -> u64 {
+    content
+        .lines()
+        .skip(2)
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(name, _)| name.trim() != "lo")
+        .map(|(_, counters)| {
+            let counters: Vec<u64> = counters.split_whitespace().filter_map(|n| n.parse().ok()).collect();
+            counters.first().unwrap_or(&0) + counters.get(8).unwrap_or(&0)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 5000000    4000    0    0    0     0          0         0  5000000    4000    0    0    0     0       0          0
+  eth0: 1200000    1000    0    0    0     0          0         0   300000     800    0    0    0     0       0          0
+ wlan0:    4000      20    0    0    0     0          0         0     1000      10    0    0    0     0       0          0
+";
+
+    #[test]
+    fn net_dev_skips_loopback() {
+        assert_eq!(parse_net_dev(NET_DEV), 1_505_000);
+    }
+
+    #[test]
+    fn dips_are_bridged_until_grace_expires() {
+        let mut rule = NetworkRule::new(&NetworkConfig {
+            min_kib_per_sec: 100,
+            grace_secs: 30,
+        });
+        let start = Instant::now();
+        let mut bytes = 0;
+        // 每 5 秒检查一次，`kib_per_sec` 是这段时间的速率，单位为 KiB/s
+        let mut step = |rule: &mut NetworkRule, n: u64, kib_per_sec: u64| {
+            bytes += kib_per_sec * 1024 * 5;
+            rule.update(start + Duration::from_secs(n * 5), bytes)
+        };
+        assert!(!rule.update(start, 0));
+        assert!(!step(&mut rule, 1, 80));
+        assert!(step(&mut rule, 2, 150));
+        // 不低于阈值的一半时继续保持
+        assert!(step(&mut rule, 3, 60));
+        // 低于一半后还有 30 秒的宽限
+        assert!(step(&mut rule, 4, 10));
+        assert!(step(&mut rule, 5, 10));
+        // 宽限期内恢复后重新计算
+        assert!(step(&mut rule, 6, 120));
+        for n in 7..13 {
+            assert!(step(&mut rule, n, 0));
+        }
+        assert!(!step(&mut rule, 13, 0));
+    }
+}
@@ -0,0 +1,200 @@
+//! 进程规则：有匹配的进程在运行时保持唤醒
+//!
+//! Linux 上遍历 `/proc`，Windows 上使用 toolhelp 快照。
+
+use super::{Rule, Snapshot};
+use crate::app::i18n::Translations;
+use std::io;
+use std::path::PathBuf;
+
+/// 正在运行的进程
+#[derive(Clone, Debug)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// 可执行文件名，例如 `ffmpeg` 或 `ffmpeg.exe`
+    pub name: String,
+    /// 可执行文件的完整路径，没有权限读取时为 `None`
+    pub path: Option<PathBuf>,
+}
+
+/// 有名称或路径匹配 `pattern` 的进程在运行时满足
+///
+/// 模式中含有路径分隔符时匹配完整路径，否则只匹配文件名。Windows 上不区分大小写。
+pub struct ProcessRule {
+    pattern: String,
+}
+
+impl ProcessRule {
+    pub fn new(pattern: &str) -> Self {
+        ProcessRule {
+            pattern: normalize(pattern),
+        }
+    }
+
+    /// 模式中含有路径分隔符时匹配完整路径
+    fn matches_path(&self) -> bool {
+        self.pattern.contains(['/', '\\'])
+    }
+
+    /// 进程是否匹配
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        if self.matches_path() {
+            process
+                .path
+                .as_ref()
+                .is_some_and(|path| glob_match(&self.pattern, &normalize(&path.to_string_lossy())))
+        } else {
+            glob_match(&self.pattern, &normalize(&process.name))
+        }
+    }
+}
+
+impl Rule for ProcessRule {
+    fn label(&self, t: &Translations) -> String {
+        t.get("rule_process").replace("{}", &self.pattern)
+    }
+
+    fn poll(&mut self, snapshot: &Snapshot) -> bool {
+        // 不计入自己，避免模式恰好匹配到本程序
+        snapshot.processes().is_some_and(|processes| {
+            processes
+                .iter()
+                .any(|process| process.pid != std::process::id() && self.matches(process))
+        })
+    }
+
+    fn needs_process_paths(&self) -> bool {
+        self.matches_path()
+    }
+}
+
+/// Windows 的文件名不区分大小写
+fn normalize(text: &str) -> String {
+    if cfg!(windows) {
+        text.to_lowercase()
+    } else {
+        text.to_string()
+    }
+}
+
+/// 只支持 `*` 和 `?` 的通配符匹配
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 `*` 的位置，以及它当时对应的文本位置
+    let mut star = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // 让 `*` 多吞一个字符后重试
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// 列出正在运行的进程
+///
+/// Linux 上可执行文件的链接本身就是名称的来源，读取开销很小，因此总是带有路径，忽略 `_with_paths`。
+#[cfg(target_os = "linux")]
+pub fn list_processes(_with_paths: bool) -> io::Result<Vec<ProcessInfo>> {
+    let mut processes = Vec::new();
+    for entry in std::fs::read_dir("/proc")? {
+        let entry = entry?;
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let dir = entry.path();
+        let path = std::fs::read_link(dir.join("exe")).ok();
+        // 其他用户的进程读不到 exe，退回到命令行的第一个参数，最后才用可能被截断的 comm
+        let name = path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .or_else(|| {
+                let cmdline = std::fs::read(dir.join("cmdline")).ok()?;
+                let argv0 = cmdline.split(|&b| b == 0).next().filter(|arg| !arg.is_empty())?;
+                let argv0 = String::from_utf8_lossy(argv0);
+                Some(argv0.rsplit('/').next().unwrap_or_default().to_string())
+            })
+            .or_else(|| std::fs::read_to_string(dir.join("comm")).ok().map(|comm| comm.trim_end().to_string()));
+        // 进程可能在遍历期间退出
+        if let Some(name) = name {
+            processes.push(ProcessInfo { pid, name, path });
+        }
+    }
+    Ok(processes)
+}
+
+/// 列出正在运行的进程，`with_paths` 为 false 时不打开进程查询路径
+#[cfg(windows)]
+pub fn list_processes(with_paths: bool) -> io::Result<Vec<ProcessInfo>> {
+    crate::app::win_api::list_processes(with_paths)
+}
+
+/// 列出正在运行的进程
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn list_processes(_with_paths: bool) -> io::Result<Vec<ProcessInfo>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "当前平台不支持枚举进程"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(name: &str, path: Option<&str>) -> ProcessInfo {
+        ProcessInfo {
+            pid: 1,
+            name: name.to_string(),
+            path: path.map(PathBuf::from),
+        }
+    }
+
+    #[test]
+    fn glob_supports_wildcards() {
+        assert!(glob_match("ffmpeg", "ffmpeg"));
+        assert!(!glob_match("ffmpeg", "ffmpeg2"));
+        assert!(glob_match("ff*", "ffmpeg"));
+        assert!(glob_match("*peg", "ffmpeg"));
+        assert!(glob_match("f?mp*g", "ffmpeg"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("*x*", "ffmpeg"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+    }
+
+    #[test]
+    fn pattern_with_separator_matches_path() {
+        let rsync = process("rsync", Some("/usr/bin/rsync"));
+        assert!(ProcessRule::new("rsync").matches(&rsync));
+        assert!(ProcessRule::new("/usr/bin/*").matches(&rsync));
+        assert!(!ProcessRule::new("/opt/*").matches(&rsync));
+        // 读不到路径的进程不匹配路径模式
+        assert!(!ProcessRule::new("/usr/bin/*").matches(&process("rsync", None)));
+        assert!(ProcessRule::new("/usr/bin/*").needs_process_paths());
+        assert!(!ProcessRule::new("rsync").needs_process_paths());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn rule_follows_process_lifetime() {
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let mut rule = ProcessRule::new("sleep");
+        let pid = child.id();
+        assert!(list_processes(false).unwrap().iter().any(|p| p.pid == pid && rule.matches(p)));
+        assert!(rule.poll(&Snapshot::new(false)));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(!list_processes(false).unwrap().iter().any(|p| p.pid == pid));
+    }
+}
@@ -0,0 +1,225 @@
+//! 保存和恢复上次的会话
+//!
+//! 会话保存在 Windows 的 `%LOCALAPPDATA%\Keep Screen\session.json`，
+//! 或 Linux 的 `$XDG_STATE_HOME/keep-screen/session.json`。
+//! 程序更新、崩溃或重启后按配置中的 [`Startup`] 决定是否恢复。
+
+use super::state::{AppState, DurationOption};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// 启动时如何设置保持亮屏的状态
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Startup {
+    /// 总是以关闭状态启动
+    Inactive,
+    /// 总是以永久保持启动
+    Active,
+    /// 恢复上次退出时的会话，计时已经结束时以关闭状态启动
+    Restore,
+}
+
+/// 需要跨重启保存的那部分状态
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub is_active: bool,
+    pub duration: DurationOption,
+    /// 计时结束的时间
+    pub deadline: Option<DateTime<Utc>>,
+    /// 暂停时冻结的剩余秒数
+    pub paused_secs: Option<u64>,
+}
+
+impl Session {
+    pub fn of(state: &AppState) -> Self {
+        Session {
+            is_active: state.is_active,
+            duration: state.duration,
+            deadline: state.deadline,
+            paused_secs: state.paused.map(|paused| paused.as_secs()),
+        }
+    }
+}
+
+/// 按启动策略和保存的会话设置初始状态
+///
+/// 恢复的计时需要由事件循环重新启动计时器。
+pub fn restore(state: &mut AppState, startup: Startup, saved: Option<Session>, now: DateTime<Utc>) {
+    match startup {
+        Startup::Inactive => state.is_active = false,
+        Startup::Active => state.is_active = true,
+        Startup::Restore => {
+            let Some(saved) = saved else {
+                return;
+            };
+            state.duration = saved.duration;
+            state.paused = saved.paused_secs.map(Duration::from_secs);
+            state.deadline = saved.deadline;
+            // 截止时间在程序没有运行时已经过去，不补执行计时结束的操作
+            state.is_active = saved.is_active && saved.deadline.is_none_or(|deadline| deadline > now);
+            if !state.is_active {
+                state.paused = None;
+                state.deadline = None;
+            }
+        }
+    }
+}
+
+/// 会话文件，只在内容变化时写入
+///
+/// 事件循环每处理完一个事件保存一次；找不到默认目录时没有会话文件，
+/// 这时不保存，下次启动也不恢复。
+pub struct SessionStore {
+    path: PathBuf,
+    last: Option<Session>,
+}
+
+impl SessionStore {
+    pub fn new(path: PathBuf) -> Self {
+        SessionStore { path, last: None }
+    }
+
+    /// 默认位置的会话文件，找不到目录时为 `None`
+    pub fn open_default() -> Option<Self> {
+        session_path().map(Self::new)
+    }
+
+    /// 读取保存的会话，文件不存在或无法解析时为 `None`
+    pub fn load(&mut self) -> Option<Session> {
+        let content = std::fs::read_to_string(&self.path).ok()?;
+        match serde_json::from_str::<Session>(&content) {
+            Ok(session) => {
+                self.last = Some(session.clone());
+                Some(session)
+            }
+            Err(e) => {
+                eprintln!("解析会话文件 {} 失败: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// 保存会话，与上次相同时不写入
+    pub fn save(&mut self, session: &Session) -> io::Result<()> {
+        if self.last.as_ref() == Some(session) {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(session).map_err(io::Error::other)?;
+        // 先写临时文件再替换，避免写到一半时退出留下损坏的文件
+        let temp = self.path.with_extension("json.tmp");
+        std::fs::write(&temp, content)?;
+        std::fs::rename(&temp, &self.path)?;
+        self.last = Some(session.clone());
+        Ok(())
+    }
+}
+
+/// 会话文件的路径
+fn session_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("Keep Screen").join("session.json"))
+    } else {
+        std::env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))
+            .map(|dir| dir.join("keep-screen").join("session.json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveTime, TimeDelta};
+
+    fn timed(deadline: DateTime<Utc>) -> Session {
+        Session {
+            is_active: true,
+            duration: DurationOption::Until(NaiveTime::from_hms_opt(18, 0, 0).unwrap()),
+            deadline: Some(deadline),
+            paused_secs: None,
+        }
+    }
+
+    #[test]
+    fn restores_running_deadline() {
+        let now = Utc::now();
+        let saved = timed(now + TimeDelta::minutes(20));
+        let mut state = AppState::new();
+        restore(&mut state, Startup::Restore, Some(saved.clone()), now);
+        assert!(state.is_active);
+        assert_eq!(state.duration, saved.duration);
+        assert_eq!(state.deadline, saved.deadline);
+    }
+
+    #[test]
+    fn expired_session_starts_inactive() {
+        let now = Utc::now();
+        let mut state = AppState::new();
+        restore(&mut state, Startup::Restore, Some(timed(now - TimeDelta::minutes(1))), now);
+        assert!(!state.is_active);
+        assert_eq!(state.deadline, None);
+        // 选中的时长仍然保留
+        assert!(matches!(state.duration, DurationOption::Until(_)));
+    }
+
+    #[test]
+    fn policy_overrides_saved_session() {
+        let now = Utc::now();
+        let mut state = AppState::new();
+        restore(&mut state, Startup::Inactive, Some(timed(now + TimeDelta::hours(1))), now);
+        assert!(!state.is_active);
+        assert_eq!(state.deadline, None);
+
+        let mut state = AppState {
+            is_active: false,
+            ..AppState::new()
+        };
+        restore(&mut state, Startup::Active, None, now);
+        assert!(state.is_active);
+
+        // 没有保存过会话时使用默认状态
+        let mut state = AppState::new();
+        restore(&mut state, Startup::Restore, None, now);
+        assert!(state.is_active);
+        assert_eq!(state.duration, DurationOption::Permanent);
+    }
+
+    #[test]
+    fn paused_session_is_restored() {
+        let saved = Session {
+            is_active: true,
+            duration: DurationOption::Minutes(60),
+            deadline: None,
+            paused_secs: Some(25 * 60),
+        };
+        let mut state = AppState::new();
+        restore(&mut state, Startup::Restore, Some(saved), Utc::now());
+        assert!(state.is_active);
+        assert_eq!(state.paused, Some(Duration::from_secs(25 * 60)));
+    }
+
+    #[test]
+    fn store_round_trips_and_skips_unchanged() {
+        let dir = std::env::temp_dir().join(format!("keep-screen-session-{}", std::process::id()));
+        let path = dir.join("session.json");
+        let session = timed(Utc::now());
+
+        let mut store = SessionStore::new(path.clone());
+        assert_eq!(store.load(), None);
+        store.save(&session).unwrap();
+        assert_eq!(SessionStore::new(path.clone()).load(), Some(session.clone()));
+
+        // 内容没有变化时不再写入
+        std::fs::remove_file(&path).unwrap();
+        store.save(&session).unwrap();
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,6 +1,11 @@
 //! 应用的状态定义模块
 
+use super::clock;
 use super::i18n::{self, Translations};
+use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// 菜单事件的枚举
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -8,52 +13,249 @@ pub enum Event {
     ShowMenu,
     ToggleActive,
     SetDuration(DurationOption),
+    TogglePause, // 暂停计时并允许睡眠，或带着剩余时间继续
+    Extend(u32), // 推迟计时结束的时间，单位为分钟
+    SetMode(KeepMode),
+    SetExpiryAction(ExpiryAction),
+    OpenConfig, // 打开配置文件以添加自定义时长或“保持到”时间
     ThemeChanged, // 系统主题变化
+    TimerExpired(u64), // 计时结束，携带计时器的代数
+    Tick, // 计时期间每分钟一次，用于刷新剩余时间
+    RuleChanged(usize, bool), // 第几条自动规则是否满足
+    ToggleProcessWait(u32), // 开始或取消等待进程退出
+    ProcessExited(u32), // 等待的进程已经退出
+    Resumed, // 系统从睡眠中唤醒或恢复了上次的会话，需要按本地时间重新检查计时
+    Verify, // 定期确认保持唤醒仍然生效
     Exit,
     NoOp, // 空操作事件
 }
 
 /// 可选的持续时间
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DurationOption {
     Permanent,
     Minutes(u32),
+    /// 保持到下一次到达这个本地时间
+    Until(NaiveTime),
 }
 
 impl DurationOption {
-    pub fn to_seconds(self) -> Option<u64> {
+    /// 从 `now` 开始计算的秒数，永久保持时为 `None`
+    pub fn seconds_from<Tz: TimeZone>(self, now: &DateTime<Tz>) -> Option<u64> {
         match self {
             DurationOption::Permanent => None,
             DurationOption::Minutes(m) => Some(m as u64 * 60),
+            DurationOption::Until(time) => Some(clock::seconds_until(now, time)),
         }
     }
 
     pub fn display_text(&self, t: &Translations) -> String {
-        match self {
+        match *self {
             DurationOption::Permanent => t.get("permanent"),
-            DurationOption::Minutes(15) => t.get("minutes_15"),
-            DurationOption::Minutes(30) => t.get("minutes_30"),
-            DurationOption::Minutes(60) => t.get("hour_1"),
-            DurationOption::Minutes(120) => t.get("hours_2"),
-            _ => "Unknown".to_string(),
+            DurationOption::Minutes(m) if m < 60 => plural(t, "minutes", m),
+            DurationOption::Minutes(m) if m % 60 == 0 => plural(t, "hours", m / 60),
+            DurationOption::Minutes(m) => format!("{} {}", plural(t, "hours", m / 60), plural(t, "minutes", m % 60)),
+            DurationOption::Until(time) => t.get("until").replace("{}", &format_time(time, t)),
+        }
+    }
+}
+
+/// 按系统区域设置中的格式显示时间，读不到时使用当前语言的格式
+pub fn format_time(time: NaiveTime, t: &Translations) -> String {
+    #[cfg(windows)]
+    if let Some(text) = super::win_api::format_time(chrono::Timelike::hour(&time), chrono::Timelike::minute(&time)) {
+        return text;
+    }
+    time.format(&t.time_format()).to_string()
+}
+
+/// 按数量选择单复数形式的翻译，例如 `minutes_one` 或 `minutes_other`
+pub fn plural(t: &Translations, key: &str, n: u32) -> String {
+    let form = if n == 1 { "one" } else { "other" };
+    t.get(&format!("{}_{}", key, form)).replace("{}", &n.to_string())
+}
+
+/// 剩余时间的显示文本，不足一分钟的部分向上取整
+pub fn remaining_text(remaining: Duration, t: &Translations) -> String {
+    let minutes = remaining.as_secs().div_ceil(60);
+    let (h, m) = (minutes / 60, minutes % 60);
+    let time = if h > 0 {
+        t.get("hours_minutes").replace("{h}", &h.to_string())
+    } else {
+        t.get("minutes_only")
+    };
+    t.get("remaining").replace("{}", &time.replace("{m}", &m.to_string()))
+}
+
+/// 菜单中“延长”操作的分钟数
+pub const EXTEND_OPTIONS: &[u32] = &[15, 60];
+
+/// 内置的时长选项，单位为分钟
+const DEFAULT_DURATIONS: &[u32] = &[15, 30, 60, 120];
+
+/// 合并内置和自定义时长，按长短排序并去重，永久保持排在最前
+pub fn duration_options(custom_minutes: &[u32]) -> Vec<DurationOption> {
+    let mut minutes: Vec<u32> = DEFAULT_DURATIONS
+        .iter()
+        .chain(custom_minutes.iter().filter(|&&m| m > 0))
+        .copied()
+        .collect();
+    minutes.sort_unstable();
+    minutes.dedup();
+    std::iter::once(DurationOption::Permanent)
+        .chain(minutes.into_iter().map(DurationOption::Minutes))
+        .collect()
+}
+
+/// 保持唤醒的范围
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeepMode {
+    /// 保持屏幕常亮，同时阻止系统休眠
+    DisplayAndSystem,
+    /// 只阻止系统休眠，允许关闭显示器
+    SystemOnly,
+    /// 只保持屏幕常亮
+    DisplayOnly,
+}
+
+impl KeepMode {
+    pub fn display_text(&self, t: &Translations) -> String {
+        match self {
+            KeepMode::DisplayAndSystem => t.get("mode_display_and_system"),
+            KeepMode::SystemOnly => t.get("mode_system_only"),
+            KeepMode::DisplayOnly => t.get("mode_display_only"),
+        }
+    }
+
+    /// 是否需要保持屏幕常亮
+    pub fn keeps_display(self) -> bool {
+        self != KeepMode::SystemOnly
+    }
+
+    /// 是否需要阻止系统休眠
+    pub fn keeps_system(self) -> bool {
+        self != KeepMode::DisplayOnly
+    }
+}
+
+pub const KEEP_MODES: &[KeepMode] = &[
+    KeepMode::DisplayAndSystem,
+    KeepMode::SystemOnly,
+    KeepMode::DisplayOnly,
+];
+
+/// 计时结束时执行的操作
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryAction {
+    /// 只释放保持唤醒
+    Release,
+    /// 锁定会话
+    Lock,
+    /// 关闭显示器
+    DisplayOff,
+    /// 挂起系统
+    Suspend,
+    /// 运行配置中的命令
+    Command,
+}
+
+impl ExpiryAction {
+    pub fn display_text(&self, t: &Translations) -> String {
+        match self {
+            ExpiryAction::Release => t.get("expiry_release"),
+            ExpiryAction::Lock => t.get("expiry_lock"),
+            ExpiryAction::DisplayOff => t.get("expiry_display_off"),
+            ExpiryAction::Suspend => t.get("expiry_suspend"),
+            ExpiryAction::Command => t.get("expiry_command"),
         }
     }
 }
 
-pub const DURATION_OPTIONS: &[DurationOption] = &[
-    DurationOption::Permanent,
-    DurationOption::Minutes(15),
-    DurationOption::Minutes(30),
-    DurationOption::Minutes(60),
-    DurationOption::Minutes(120),
+pub const EXPIRY_ACTIONS: &[ExpiryAction] = &[
+    ExpiryAction::Release,
+    ExpiryAction::Lock,
+    ExpiryAction::DisplayOff,
+    ExpiryAction::Suspend,
+    ExpiryAction::Command,
 ];
 
+/// 计时在系统睡眠期间到期、唤醒后才发现时如何处理
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedExpiry {
+    /// 和正常到期一样，释放后执行计时结束的操作
+    Expire,
+    /// 只释放，不执行锁屏、睡眠等操作，避免刚唤醒就再次锁屏或睡眠
+    Release,
+    /// 继续保持，从唤醒时起重新计时 [`EXTEND_OPTIONS`] 的第一项
+    Extend,
+}
+
+/// 自动保持唤醒的规则及其当前是否满足
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct RuleStatus {
+    /// 显示在菜单中的说明
+    pub label: String,
+    pub matched: bool,
+}
+
+/// 保持唤醒未能生效的原因，显示在菜单和提示中
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Warning {
+    /// 后端申请失败
+    AcquireFailed(String),
+    /// 申请成功，但读回系统状态时找不到
+    NotConfirmed,
+}
+
+impl Warning {
+    pub fn display_text(&self, t: &Translations) -> String {
+        match self {
+            Warning::AcquireFailed(error) => t.get("warning_acquire_failed").replace("{}", error),
+            Warning::NotConfirmed => t.get("warning_not_confirmed"),
+        }
+    }
+}
+
 /// 保存应用当前状态的结构体
+///
+/// 只包含纯数据，事件通过 [`reduce`](super::reducer::reduce) 产生新的状态。
+#[derive(Clone)]
 pub struct AppState {
     pub is_active: bool,
     pub duration: DurationOption,
-    pub translations: Translations,
-    pub timer_shutdown_tx: Option<crossbeam_channel::Sender<()>>,
+    /// 菜单中可选的时长
+    pub duration_options: Vec<DurationOption>,
+    /// 配置中添加的“保持到”时间
+    pub until_times: Vec<NaiveTime>,
+    /// 计时结束时执行的操作
+    pub expiry_action: ExpiryAction,
+    /// [`ExpiryAction::Command`] 运行的命令，未配置时菜单中不提供该选项
+    pub expiry_command: Option<String>,
+    pub mode: KeepMode,
+    pub warning: Option<Warning>,
+    /// 当前计时器的代数，每次启动或停止计时器都会加一，旧计时器的到期事件会被忽略
+    pub timer_generation: u64,
+    /// 计时结束的时间，永久保持或未开启时为 `None`
+    ///
+    /// 使用本地时间而不是单调时钟，系统睡眠期间经过的时间同样计算在内。
+    pub deadline: Option<DateTime<Utc>>,
+    /// 计时在系统睡眠期间到期时如何处理
+    pub missed_expiry: MissedExpiry,
+    /// 暂停时冻结的剩余时间；暂停期间不持有保持唤醒，也没有截止时间
+    pub paused: Option<Duration>,
+    /// 配置中的自动规则，顺序与 [`Event::RuleChanged`] 的序号一致
+    pub rules: Vec<RuleStatus>,
+    /// 正在等待退出的进程，有任何一个没有退出时保持唤醒
+    pub waiting_pids: Vec<u32>,
+    /// 计时结束前多少分钟发出通知，为 0 时不通知
+    pub warn_before_minutes: u32,
+    /// 本次计时是否已经发出过即将结束的通知
+    pub expiry_warned: bool,
+    pub translations: Arc<Translations>,
 }
 
 impl AppState {
@@ -61,8 +263,116 @@ impl AppState {
         AppState {
             is_active: true, // 默认开启
             duration: DurationOption::Permanent,
-            translations: i18n::load(),
-            timer_shutdown_tx: None,
+            duration_options: duration_options(&[]),
+            until_times: Vec::new(),
+            expiry_action: ExpiryAction::Release,
+            expiry_command: None,
+            mode: KeepMode::DisplayAndSystem,
+            warning: None,
+            timer_generation: 0,
+            deadline: None,
+            missed_expiry: MissedExpiry::Release,
+            paused: None,
+            rules: Vec::new(),
+            waiting_pids: Vec::new(),
+            warn_before_minutes: 5,
+            expiry_warned: false,
+            translations: Arc::new(i18n::load()),
         }
     }
+
+    /// 菜单中可选的“保持到”时间：接下来的几个整点、配置中的时间和当前选中的时间，
+    /// 按到达的先后排序
+    pub fn until_options<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Vec<DurationOption> {
+        let mut times = clock::upcoming_round_times(now, 4);
+        times.extend(&self.until_times);
+        if let DurationOption::Until(time) = self.duration {
+            times.push(time);
+        }
+        times.sort_by_key(|&time| clock::seconds_until(now, time));
+        times.dedup();
+        times.into_iter().map(DurationOption::Until).collect()
+    }
+
+    /// 当前是否应当持有保持唤醒：已开启且没有暂停，或者有自动规则满足，或者在等待进程退出
+    pub fn holds_keep_awake(&self) -> bool {
+        (self.is_active && self.paused.is_none()) || self.rule_hold().is_some() || !self.waiting_pids.is_empty()
+    }
+
+    /// 第一条满足的自动规则
+    pub fn rule_hold(&self) -> Option<&RuleStatus> {
+        self.rules.iter().find(|rule| rule.matched)
+    }
+
+    /// 距离计时结束还有多久
+    pub fn remaining<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.signed_duration_since(now).to_std().unwrap_or(Duration::ZERO))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaining_text_rounds_up_to_minutes() {
+        let t = i18n::english();
+        assert_eq!(remaining_text(Duration::from_secs(72 * 60), &t), "Remaining: 1h 12m");
+        assert_eq!(remaining_text(Duration::from_secs(14 * 60 + 1), &t), "Remaining: 15m");
+        assert_eq!(remaining_text(Duration::from_secs(120 * 60), &t), "Remaining: 2h 0m");
+        assert_eq!(remaining_text(Duration::ZERO, &t), "Remaining: 0m");
+    }
+
+    #[test]
+    fn duration_text_is_pluralized() {
+        let t = i18n::english();
+        let text = |m| DurationOption::Minutes(m).display_text(&t);
+        assert_eq!(text(1), "1 Minute");
+        assert_eq!(text(45), "45 Minutes");
+        assert_eq!(text(60), "1 Hour");
+        assert_eq!(text(180), "3 Hours");
+        assert_eq!(text(61), "1 Hour 1 Minute");
+        assert_eq!(text(150), "2 Hours 30 Minutes");
+        assert_eq!(DurationOption::Permanent.display_text(&t), "Always");
+    }
+
+    #[test]
+    fn until_text_uses_locale_format() {
+        let t = i18n::english();
+        let time = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+        assert_eq!(DurationOption::Until(time).display_text(&t), "Until 6:00 PM");
+    }
+
+    #[test]
+    fn until_options_are_ordered_by_arrival() {
+        let hm = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let state = AppState {
+            until_times: vec![hm(18, 0), hm(8, 30)],
+            duration: DurationOption::Until(hm(21, 0)),
+            ..AppState::new()
+        };
+        let now = chrono::Utc.with_ymd_and_hms(2024, 5, 1, 17, 10, 0).unwrap();
+        let times: Vec<_> = [hm(18, 0), hm(19, 0), hm(20, 0), hm(21, 0), hm(8, 30)]
+            .into_iter()
+            .map(DurationOption::Until)
+            .collect();
+        assert_eq!(state.until_options(&now), times);
+    }
+
+    #[test]
+    fn custom_durations_are_merged_and_sorted() {
+        assert_eq!(
+            duration_options(&[480, 45, 30, 0]),
+            vec![
+                DurationOption::Permanent,
+                DurationOption::Minutes(15),
+                DurationOption::Minutes(30),
+                DurationOption::Minutes(45),
+                DurationOption::Minutes(60),
+                DurationOption::Minutes(120),
+                DurationOption::Minutes(480),
+            ]
+        );
+    }
 }
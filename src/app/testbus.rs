@@ -0,0 +1,195 @@
+//! 测试用的私有 D-Bus 会话总线和模拟服务
+//!
+//! 系统中没有 dbus-daemon 时依赖它的测试会失败，以免缺少守护进程时看起来像通过；
+//! 确实无法安装时可以设置 `KEEP_SCREEN_SKIP_DBUS` 跳过这些测试。
+
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::UnixStream;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use zbus::blocking::{connection, Connection};
+use zbus::object_server::Interface;
+use zbus::zvariant::OwnedFd;
+
+/// 独立运行的 dbus-daemon 进程，离开作用域时自动结束
+pub struct PrivateBus {
+    child: Child,
+    address: String,
+}
+
+impl PrivateBus {
+    /// 启动一个私有总线；没有 dbus-daemon 时让测试失败，设置了 `KEEP_SCREEN_SKIP_DBUS` 时返回 `None`
+    pub fn start() -> Option<Self> {
+        match Self::spawn() {
+            Some(bus) => Some(bus),
+            None => {
+                assert!(
+                    std::env::var_os("KEEP_SCREEN_SKIP_DBUS").is_some(),
+                    "未找到 dbus-daemon；无法安装时设置 KEEP_SCREEN_SKIP_DBUS 跳过相关测试"
+                );
+                eprintln!("未找到 dbus-daemon，按 KEEP_SCREEN_SKIP_DBUS 跳过测试");
+                None
+            }
+        }
+    }
+
+    fn spawn() -> Option<Self> {
+        let mut child = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(child.stdout.take()?).read_line(&mut address).ok()?;
+        Some(PrivateBus {
+            child,
+            address: address.trim().to_string(),
+        })
+    }
+
+    /// 返回连接到这条总线的构建器
+    pub fn builder(&self) -> connection::Builder<'_> {
+        connection::Builder::address(self.address.as_str()).unwrap()
+    }
+
+    /// 以普通客户端身份连接
+    pub fn connect(&self) -> Connection {
+        self.builder().build().unwrap()
+    }
+
+    /// 以 `name` 的所有者身份在 `path` 上提供模拟接口，返回的连接断开时服务随之消失
+    pub fn serve<I: Interface>(&self, name: &'static str, path: &'static str, iface: I) -> Connection {
+        self.builder()
+            .name(name)
+            .unwrap()
+            .serve_at(path, iface)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// 模拟服务收到的调用，按 `Method(参数)` 的格式记录
+#[derive(Clone, Default)]
+pub struct CallLog(Arc<Mutex<Vec<String>>>);
+
+impl CallLog {
+    pub fn push(&self, call: String) {
+        self.0.lock().unwrap().push(call);
+    }
+
+    /// 到目前为止记录的调用
+    pub fn calls(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// 等到至少有 `count` 个调用，最多等 5 秒，用于后台线程发出的调用
+    pub fn wait_for(&self, count: usize) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while self.0.lock().unwrap().len() < count && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        self.calls()
+    }
+}
+
+/// 模拟的 `org.freedesktop.ScreenSaver` 服务，cookie 从 1 开始递增
+#[derive(Clone, Default)]
+pub struct StubScreenSaver {
+    pub calls: CallLog,
+    next_cookie: Arc<Mutex<u32>>,
+}
+
+#[zbus::interface(name = "org.freedesktop.ScreenSaver")]
+impl StubScreenSaver {
+    fn inhibit(&self, _application_name: &str, reason_for_inhibit: &str) -> u32 {
+        let mut cookie = self.next_cookie.lock().unwrap();
+        *cookie += 1;
+        self.calls.push(format!("Inhibit({}) -> {}", reason_for_inhibit, cookie));
+        *cookie
+    }
+
+    #[zbus(name = "UnInhibit")]
+    fn un_inhibit(&self, cookie: u32) {
+        self.calls.push(format!("UnInhibit({})", cookie));
+    }
+}
+
+impl StubScreenSaver {
+    pub fn serve(&self, bus: &PrivateBus) -> Connection {
+        bus.serve("org.freedesktop.ScreenSaver", "/org/freedesktop/ScreenSaver", self.clone())
+    }
+}
+
+/// `ListInhibitors` 返回的一项：(what, who, why, mode, uid, pid)
+type Inhibitor = (String, String, String, String, u32, u32);
+
+/// 模拟的 `org.freedesktop.login1.Manager` 服务
+///
+/// 每个抑制锁的另一端都保留在 `peers` 中，测试可以检查锁是否已被关闭。
+#[derive(Clone, Default)]
+pub struct StubLogin1 {
+    pub calls: CallLog,
+    pub peers: Arc<Mutex<Vec<UnixStream>>>,
+    /// 持有过的锁，`ListInhibitors` 返回其中最后一个
+    inhibitors: Arc<Mutex<Vec<Inhibitor>>>,
+    /// 为 true 时假装锁被策略拒绝，不出现在列表中
+    drop_locks: bool,
+}
+
+#[zbus::interface(name = "org.freedesktop.login1.Manager")]
+impl StubLogin1 {
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::fdo::Result<OwnedFd> {
+        let (ours, theirs) = UnixStream::pair().map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+        self.calls.push(format!("Inhibit({}, {}, {})", what, why, mode));
+        self.inhibitors.lock().unwrap().push((
+            what.to_string(),
+            who.to_string(),
+            why.to_string(),
+            mode.to_string(),
+            0,
+            std::process::id(),
+        ));
+        self.peers.lock().unwrap().push(theirs);
+        Ok(std::os::fd::OwnedFd::from(ours).into())
+    }
+
+    fn list_inhibitors(&self) -> Vec<Inhibitor> {
+        if self.drop_locks {
+            return Vec::new();
+        }
+        self.inhibitors.lock().unwrap().last().cloned().into_iter().collect()
+    }
+
+    fn lock_session(&self, session_id: &str) {
+        self.calls.push(format!("LockSession({})", session_id));
+    }
+
+    fn suspend(&self, interactive: bool) {
+        self.calls.push(format!("Suspend({})", interactive));
+    }
+}
+
+impl StubLogin1 {
+    /// 申请总是成功，但锁不出现在 `ListInhibitors` 中，如同被策略拒绝
+    pub fn dropping_locks() -> Self {
+        StubLogin1 {
+            drop_locks: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn serve(&self, bus: &PrivateBus) -> Connection {
+        bus.serve("org.freedesktop.login1", "/org/freedesktop/login1", self.clone())
+    }
+}
@@ -0,0 +1,104 @@
+//! 测试用的 Xvfb 虚拟显示和无头 Wayland 合成器
+//!
+//! 开发环境通常没有这两个程序，依赖它们的测试默认忽略，安装后用 `cargo test -- --ignored` 运行。
+//! 运行时找不到程序会让测试失败，而不是悄悄跳过。
+
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 独立运行的 Xvfb 进程，离开作用域时自动结束
+pub struct Xvfb {
+    child: Child,
+    display: String,
+}
+
+impl Xvfb {
+    /// 在空闲的显示编号上启动 Xvfb
+    pub fn start() -> Self {
+        Self::spawn().expect("无法启动 Xvfb")
+    }
+
+    fn spawn() -> Option<Self> {
+        let mut child = Command::new("Xvfb")
+            .args(["-displayfd", "1", "-nolisten", "tcp", "+extension", "DPMS"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut number = String::new();
+        BufReader::new(child.stdout.take()?).read_line(&mut number).ok()?;
+        Some(Xvfb {
+            child,
+            display: format!(":{}", number.trim()),
+        })
+    }
+
+    /// 可以传给 `x11rb::connect` 的显示名称
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+}
+
+impl Drop for Xvfb {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// 使用无头后端运行的 weston，离开作用域时自动结束
+pub struct Weston {
+    child: Child,
+    runtime_dir: PathBuf,
+}
+
+impl Weston {
+    /// 在独立的运行时目录中启动 weston
+    pub fn start() -> Self {
+        Self::spawn().expect("无法启动 weston")
+    }
+
+    fn spawn() -> Option<Self> {
+        let runtime_dir = std::env::temp_dir().join(format!("keep-screen-weston-{}", std::process::id()));
+        std::fs::create_dir_all(&runtime_dir).ok()?;
+        let child = Command::new("weston")
+            .args(["--backend=headless", "--socket=wayland-test", "--idle-time=0"])
+            .env("XDG_RUNTIME_DIR", &runtime_dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let weston = Weston { child, runtime_dir };
+
+        // 等待合成器创建套接字
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !weston.socket().exists() {
+            if Instant::now() > deadline {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        Some(weston)
+    }
+
+    fn socket(&self) -> PathBuf {
+        self.runtime_dir.join("wayland-test")
+    }
+
+    /// 连接合成器的套接字
+    pub fn connect(&self) -> UnixStream {
+        UnixStream::connect(self.socket()).unwrap()
+    }
+}
+
+impl Drop for Weston {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.runtime_dir);
+    }
+}
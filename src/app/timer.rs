@@ -1,34 +1,164 @@
 //! 计时器管理模块
+//!
+//! 截止时间按本地时间保存，计时器线程每次醒来都重新读取时间，
+//! 因此系统睡眠期间经过的时间也会计算在内。
 
-use super::state::{AppState, Event};
+use super::clock::Clock;
+use super::state::Event;
+use chrono::{DateTime, Utc};
+use crossbeam_channel::{RecvTimeoutError, Sender};
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
-/// 启动计时器线程（如果时长不是永久）
-pub fn start_timer_thread(state: &mut AppState, event_tx: crossbeam_channel::Sender<Event>) {
-    stop_timer_thread(state);
+/// 计时期间发送 [`Event::Tick`] 的间隔
+const TICK_INTERVAL: Duration = Duration::from_secs(60);
 
-    if let Some(seconds) = state.duration.to_seconds() {
-        let (shutdown_tx, shutdown_rx) = crossbeam_channel::unbounded();
-        state.timer_shutdown_tx = Some(shutdown_tx);
+/// 计时器线程最长的等待时间，醒来后检查系统是否睡眠过
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
 
+/// 本地时间比单调时钟多走超过这么久时认为系统刚从睡眠中唤醒
+const RESUME_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// 距离下一个整分钟剩余时间的间隔，使 Tick 恰好落在剩余 N 分钟的时刻
+fn until_next_tick(remaining: Duration) -> Duration {
+    let nanos = (remaining.as_nanos() % TICK_INTERVAL.as_nanos()) as u64;
+    if nanos == 0 {
+        TICK_INTERVAL
+    } else {
+        Duration::from_nanos(nanos)
+    }
+}
+
+/// 到时后关闭保持亮屏的计时器，同一时间最多运行一个
+pub struct Timer {
+    clock: Arc<dyn Clock>,
+    poll_interval: Duration,
+    shutdown_tx: Option<Sender<()>>,
+}
+
+impl Timer {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Timer {
+            clock,
+            poll_interval: POLL_INTERVAL,
+            shutdown_tx: None,
+        }
+    }
+
+    /// 启动计时器线程，替换正在运行的计时器
+    ///
+    /// 期间每当剩余时间到达整分钟时发送 [`Event::Tick`]，发现系统睡眠过时发送 [`Event::Resumed`]，
+    /// 到达 `deadline` 后发送带有 `generation` 的 [`Event::TimerExpired`]。
+    pub fn start(&mut self, deadline: DateTime<Utc>, generation: u64, event_tx: Sender<Event>) {
+        self.stop();
+
+        let (shutdown_tx, shutdown_rx) = crossbeam_channel::unbounded::<()>();
+        self.shutdown_tx = Some(shutdown_tx);
+
+        let clock = Arc::clone(&self.clock);
+        let poll_interval = self.poll_interval;
+        let mut last = clock.now();
+        // 剩余时间降到这个值时发送下一次 Tick
+        let mut next_tick = next_tick_at(last.until(deadline));
         thread::spawn(move || {
-            match shutdown_rx.recv_timeout(Duration::from_secs(seconds)) {
-                Ok(_) => {
+            loop {
+                let wait = last.until(deadline).saturating_sub(next_tick).min(poll_interval);
+                match shutdown_rx.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) => {}
                     // 计时器被手动停止
+                    _ => break,
+                }
+
+                let now = clock.now();
+                if now.wall_drift_since(&last) > RESUME_THRESHOLD {
+                    let _ = event_tx.send(Event::Resumed);
+                }
+                last = now;
+
+                let remaining = now.until(deadline);
+                if remaining.is_zero() {
+                    // 计时结束，发送到期事件
+                    let _ = event_tx.send(Event::TimerExpired(generation));
+                    break;
                 }
-                Err(_) => {
-                    // 计时结束，发送关闭亮屏事件
-                    let _ = event_tx.send(Event::ToggleActive);
+                if remaining <= next_tick {
+                    let _ = event_tx.send(Event::Tick);
+                    next_tick = next_tick_at(remaining);
                 }
             }
         });
     }
+
+    /// 停止当前的计时器线程
+    pub fn stop(&mut self) {
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            let _ = shutdown_tx.send(());
+        }
+    }
+
+    /// 是否有计时器正在运行
+    #[cfg(test)]
+    pub fn is_running(&self) -> bool {
+        self.shutdown_tx.is_some()
+    }
 }
 
-/// 停止当前的计时器线程
-pub fn stop_timer_thread(state: &mut AppState) {
-    if let Some(shutdown_tx) = state.timer_shutdown_tx.take() {
-        let _ = shutdown_tx.send(());
+/// 剩余 `remaining` 时下一次发送 Tick 时的剩余时间
+fn next_tick_at(remaining: Duration) -> Duration {
+    remaining.saturating_sub(until_next_tick(remaining))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::clock::ManualClock;
+    use chrono::TimeDelta;
+
+    #[test]
+    fn ticks_align_to_whole_minutes_of_remaining_time() {
+        assert_eq!(until_next_tick(Duration::from_secs(5 * 60)), TICK_INTERVAL);
+        assert_eq!(until_next_tick(Duration::from_secs(5 * 60 + 20)), Duration::from_secs(20));
+        assert_eq!(until_next_tick(Duration::from_millis(59_500)), Duration::from_millis(59_500));
+        assert_eq!(next_tick_at(Duration::from_secs(5 * 60 + 20)), Duration::from_secs(5 * 60));
+        assert_eq!(next_tick_at(Duration::from_secs(30)), Duration::ZERO);
+    }
+
+    #[test]
+    fn wall_clock_deadline_survives_sleep() {
+        let clock = Arc::new(ManualClock::new());
+        let mut timer = Timer {
+            poll_interval: Duration::from_millis(5),
+            ..Timer::new(clock.clone())
+        };
+        let (event_tx, event_rx) = crossbeam_channel::unbounded();
+        let deadline = clock.now().wall.to_utc() + TimeDelta::minutes(10);
+        timer.start(deadline, 4, event_tx);
+
+        let recv = || event_rx.recv_timeout(Duration::from_secs(5));
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(recv(), Ok(Event::Tick));
+
+        // 盖上笔记本，截止时间在睡眠期间到达
+        clock.sleep(Duration::from_secs(3600));
+        assert_eq!(recv(), Ok(Event::Resumed));
+        assert_eq!(recv(), Ok(Event::TimerExpired(4)));
+    }
+
+    #[test]
+    fn short_sleep_only_resyncs() {
+        let clock = Arc::new(ManualClock::new());
+        let mut timer = Timer {
+            poll_interval: Duration::from_millis(5),
+            ..Timer::new(clock.clone())
+        };
+        let (event_tx, event_rx) = crossbeam_channel::unbounded();
+        timer.start(clock.now().wall.to_utc() + TimeDelta::hours(2), 1, event_tx);
+
+        clock.sleep(Duration::from_secs(30 * 60));
+        assert_eq!(event_rx.recv_timeout(Duration::from_secs(5)), Ok(Event::Resumed));
+        assert_eq!(event_rx.recv_timeout(Duration::from_secs(5)), Ok(Event::Tick));
+        timer.stop();
+        assert!(event_rx.recv_timeout(Duration::from_millis(100)).is_err());
     }
 }
@@ -1,31 +1,75 @@
 //! 系统托盘图标和菜单逻辑
 
-use super::i18n::Translations;
-use super::state::{AppState, DurationOption, Event, DURATION_OPTIONS};
-use super::timer::{start_timer_thread, stop_timer_thread};
-use super::win_api::{set_keep_awake, set_theme_change_callback};
+use super::config;
+use super::controller::{self, Controller, Flow};
+use super::notify;
+use super::power::PowerBackend;
+use super::rules::{self, ProcessInfo, Rule};
+use super::session::SessionStore;
+use super::state::{
+    duration_options, format_time, remaining_text, AppState, DurationOption, Event, ExpiryAction, EXPIRY_ACTIONS, EXTEND_OPTIONS, KEEP_MODES,
+};
+use super::win_api::{self, set_event_sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use trayicon::{Icon, MenuBuilder, MenuItem, TrayIconBuilder};
-
-/// 构建菜单UI
-fn build_menu(
-    is_active: bool,
-    current_duration: DurationOption,
-    t: &Translations,
-) -> MenuBuilder<Event> {
+use trayicon::{Icon, MenuBuilder, MenuItem, TrayIcon, TrayIconBuilder};
+
+/// 构建菜单UI，`processes` 是可以等待退出的进程
+fn build_menu(state: &AppState, backend_name: &str, processes: &[ProcessInfo]) -> MenuBuilder<Event> {
+    let t = &state.translations;
+    let is_active = state.is_active;
     let mut menu = MenuBuilder::new();
+    // 保持唤醒未生效时在最上方显示警告
+    if let Some(warning) = &state.warning {
+        menu = menu
+            .with(MenuItem::Item {
+                name: warning.display_text(t),
+                disabled: true,
+                id: Event::NoOp,
+                icon: None,
+            })
+            .separator();
+    }
+    if let Some(text) = remaining_line(state) {
+        menu = menu
+            .with(MenuItem::Item {
+                name: text,
+                disabled: true,
+                id: Event::NoOp,
+                icon: None,
+            })
+            .separator();
+    }
     menu = menu.checkable(&t.get("keep_screen_on"), is_active, Event::ToggleActive);
 
+    // 只有计时中的会话可以暂停
+    let timed = is_active && state.deadline.is_some();
+    let paused = is_active && state.paused.is_some();
+    menu = menu.with(MenuItem::Item {
+        name: t.get(if paused { "resume" } else { "pause" }),
+        disabled: !(timed || paused),
+        id: Event::TogglePause,
+        icon: None,
+    });
+
     let mut duration_submenu = MenuBuilder::new();
-    for &duration_opt in DURATION_OPTIONS {
-        duration_submenu = duration_submenu.checkable(
-            &duration_opt.display_text(t),
-            duration_opt == current_duration,
-            Event::SetDuration(duration_opt),
-        );
+    let until_options = state.until_options(&chrono::Local::now());
+    // 每组最后都是打开配置文件的“自定义…”，分别对应 `durations` 和 `until`
+    let groups = [(&state.duration_options, "custom_duration"), (&until_options, "custom_until")];
+    for (i, (group, custom)) in groups.into_iter().enumerate() {
+        if i > 0 {
+            duration_submenu = duration_submenu.separator();
+        }
+        for &duration_opt in group {
+            duration_submenu = duration_submenu.checkable(
+                &duration_opt.display_text(t),
+                duration_opt == state.duration,
+                Event::SetDuration(duration_opt),
+            );
+        }
+        duration_submenu = duration_submenu.item(&t.get(custom), Event::OpenConfig);
     }
-    
+
     menu = menu.with(MenuItem::Submenu {
         name: t.get("duration"),
         children: duration_submenu,
@@ -34,17 +78,216 @@ fn build_menu(
         icon: None,
     });
 
+    let mut mode_submenu = MenuBuilder::new();
+    for &mode in KEEP_MODES {
+        mode_submenu = mode_submenu.checkable(
+            &mode.display_text(t),
+            mode == state.mode,
+            Event::SetMode(mode),
+        );
+    }
+
+    // 自动规则或等待进程保持唤醒时模式同样生效
+    menu = menu.with(MenuItem::Submenu {
+        name: t.get("mode"),
+        children: mode_submenu,
+        disabled: !state.holds_keep_awake(),
+        id: Some(Event::NoOp),
+        icon: None,
+    });
+
+    let mut expiry_submenu = MenuBuilder::new();
+    for &action in EXPIRY_ACTIONS {
+        // 没有配置命令时不提供运行命令
+        if action == ExpiryAction::Command && state.expiry_command.is_none() {
+            continue;
+        }
+        expiry_submenu = expiry_submenu.checkable(
+            &action.display_text(t),
+            action == state.expiry_action,
+            Event::SetExpiryAction(action),
+        );
+    }
+
+    menu = menu.with(MenuItem::Submenu {
+        name: t.get("expiry_action"),
+        children: expiry_submenu,
+        disabled: !is_active,
+        id: Some(Event::NoOp),
+        icon: None,
+    });
+
+    // 只有计时或暂停期间才能延长
+    for &minutes in EXTEND_OPTIONS {
+        menu = menu.with(MenuItem::Item {
+            name: format!("+{}", DurationOption::Minutes(minutes).display_text(t)),
+            disabled: !(timed || paused),
+            id: Event::Extend(minutes),
+            icon: None,
+        });
+    }
+
+    // 列出自动规则，勾选当前满足的
+    if !state.rules.is_empty() {
+        let mut rules_submenu = MenuBuilder::new();
+        for rule in &state.rules {
+            rules_submenu = rules_submenu.with(MenuItem::Checkable {
+                name: rule.label.clone(),
+                is_checked: rule.matched,
+                disabled: true,
+                id: Event::NoOp,
+                icon: None,
+            });
+        }
+        menu = menu.with(MenuItem::Submenu {
+            name: t.get("rules"),
+            children: rules_submenu,
+            disabled: false,
+            id: Some(Event::NoOp),
+            icon: None,
+        });
+    }
+
+    // 选择一个进程，保持唤醒直到它退出，再次选择时取消；同名的进程收进一个子菜单，按 PID 选择
+    let mut process_submenu = MenuBuilder::new();
+    for group in processes.chunk_by(|a, b| a.name.eq_ignore_ascii_case(&b.name)) {
+        if let [process] = group {
+            process_submenu = process_submenu.checkable(
+                &format!("{} ({})", process.name, process.pid),
+                state.waiting_pids.contains(&process.pid),
+                Event::ToggleProcessWait(process.pid),
+            );
+            continue;
+        }
+        let mut group_submenu = MenuBuilder::new();
+        for process in group {
+            group_submenu = group_submenu.checkable(
+                &process.pid.to_string(),
+                state.waiting_pids.contains(&process.pid),
+                Event::ToggleProcessWait(process.pid),
+            );
+        }
+        process_submenu = process_submenu.with(MenuItem::Submenu {
+            name: t
+                .get("process_group")
+                .replace("{name}", &group[0].name)
+                .replace("{count}", &group.len().to_string()),
+            children: group_submenu,
+            disabled: false,
+            id: Some(Event::NoOp),
+            icon: None,
+        });
+    }
+    menu = menu.with(MenuItem::Submenu {
+        name: t.get("wait_process"),
+        children: process_submenu,
+        disabled: processes.is_empty(),
+        id: Some(Event::NoOp),
+        icon: None,
+    });
+
+    menu = menu.separator().with(MenuItem::Item {
+        name: t.get("backend").replace("{}", backend_name),
+        disabled: true,
+        id: Event::NoOp,
+        icon: None,
+    });
+
     menu = menu.separator().item(&t.get("exit_app"), Event::Exit);
     menu
 }
 
-/// 创建托盘图标并运行事件循环
-pub fn run_tray_event_loop(app_state: Arc<Mutex<AppState>>) {
+/// 剩余时间的说明，暂停时显示冻结的剩余时间，没有计时时为 `None`
+fn remaining_line(state: &AppState) -> Option<String> {
+    let t = &state.translations;
+    if let Some(paused) = state.paused {
+        return Some(t.get("paused").replace("{}", &remaining_text(paused, t)));
+    }
+    state
+        .remaining(&chrono::Local::now())
+        .map(|remaining| remaining_text(remaining, t))
+}
+
+/// 重新读取配置中的自定义时长和“保持到”时间，“自定义…”打开配置文件修改后不必重启
+fn reload_menu_config(state: &mut AppState) {
+    if let Some(config) = config::reload() {
+        state.duration_options = duration_options(&config.durations);
+        state.until_times = config.until;
+    }
+}
+
+/// 可以在菜单中选择等待的进程：当前登录会话中能读到路径的其他进程，按名称排序
+fn pickable_processes() -> Vec<ProcessInfo> {
+    let session = win_api::process_session_id(std::process::id());
+    let mut processes = match rules::list_processes(true) {
+        Ok(processes) => processes,
+        Err(e) => {
+            eprintln!("枚举进程失败: {}", e);
+            return Vec::new();
+        }
+    };
+    // 读不到路径的多是系统进程，通常也无法等待；其他会话中的服务和其他用户的进程不是用户启动的任务
+    processes.retain(|process| {
+        process.pid != std::process::id()
+            && process.path.is_some()
+            && win_api::process_session_id(process.pid) == session
+    });
+    processes.sort_by_cached_key(|process| (process.name.to_lowercase(), process.pid));
+    processes
+}
+
+/// 托盘图标的提示文本，依次附上结束时间、剩余时间和警告
+fn tooltip(state: &AppState, processes: &[ProcessInfo]) -> String {
+    let t = &state.translations;
+    let mut text = "Keep Screen".to_string();
+    if let Some(deadline) = state.deadline {
+        // 延长后结束时间不再等于选中的选项，按实际的截止时间显示
+        let end = deadline.with_timezone(&chrono::Local);
+        text = format!("{}\n{}", text, t.get("until").replace("{}", &format_time(end.time(), t)));
+    }
+    if let Some(line) = remaining_line(state) {
+        text = format!("{}\n{}", text, line);
+    }
+    if let Some(rule) = state.rule_hold() {
+        text = format!("{}\n{}", text, t.get("held_by_rule").replace("{}", &rule.label));
+    }
+    for pid in &state.waiting_pids {
+        let name = match processes.iter().find(|process| process.pid == *pid) {
+            Some(process) => format!("{} ({})", process.name, pid),
+            None => pid.to_string(),
+        };
+        text = format!("{}\n{}", text, t.get("held_by_process").replace("{}", &name));
+    }
+    if let Some(warning) = &state.warning {
+        text = format!("{}\n{}", text, warning.display_text(t));
+    }
+    text
+}
+
+/// 按当前状态更新菜单和提示文本
+fn refresh(tray_icon: &mut TrayIcon<Event>, state: &AppState, backend_name: &str, processes: &[ProcessInfo]) {
+    if let Err(e) = tray_icon.set_menu(&build_menu(state, backend_name, processes)) {
+        eprintln!("更新托盘菜单失败: {}", e);
+    }
+    if let Err(e) = tray_icon.set_tooltip(&tooltip(state, processes)) {
+        eprintln!("更新托盘提示失败: {}", e);
+    }
+}
+
+/// 创建托盘图标并运行事件循环，会话保存到 `session_store`
+pub fn run_tray_event_loop(
+    app_state: Arc<Mutex<AppState>>,
+    backend: Box<dyn PowerBackend>,
+    session_store: Option<SessionStore>,
+    rules: Vec<Box<dyn Rule>>,
+) {
     let (event_tx, event_rx) = crossbeam_channel::unbounded();
     let event_tx_clone = event_tx.clone();
 
-    // 设置主题变化回调，当系统主题变化时发送 ThemeChanged 事件
-    set_theme_change_callback(event_tx.clone());
+    // 消息窗口在系统主题变化时发送 ThemeChanged 事件，点击通知气泡时发送延长事件
+    set_event_sender(event_tx.clone());
+    rules::watch(rules, event_tx.clone());
+    controller::watch_backend(event_tx.clone());
 
     let icon = match Icon::from_buffer(include_bytes!("../../res/tray.ico"), None, None) {
         Ok(icon) => icon,
@@ -62,11 +305,11 @@ pub fn run_tray_event_loop(app_state: Arc<Mutex<AppState>>) {
                 return;
             }
         };
-        let menu = build_menu(state.is_active, state.duration, &state.translations);
+        let menu = build_menu(&state, &backend.name(), &[]);
         match TrayIconBuilder::new()
             .sender(move |e| { let _ = event_tx_clone.send(*e); })
             .icon(icon)
-            .tooltip("Keep Screen")
+            .tooltip(&tooltip(&state, &[]))
             .on_click(Event::ShowMenu)
             .on_right_click(Event::ShowMenu)
             .menu(menu)
@@ -82,61 +325,43 @@ pub fn run_tray_event_loop(app_state: Arc<Mutex<AppState>>) {
 
     let event_handler_state = Arc::clone(&app_state);
     thread::spawn(move || {
-        // 初始调用，确保和后续调用在同一线程
-        set_keep_awake(true);
+        // 后端的所有调用都在事件线程内进行，执行状态是按线程记录的
+        let mut controller = Controller::new(backend, notify::create(event_tx.clone()), event_tx);
+        if let Some(store) = session_store {
+            controller.set_session_store(store);
+        }
+        // 进程列表只在弹出菜单时重新读取
+        let mut processes = Vec::new();
+        if let Ok(mut state) = event_handler_state.lock() {
+            controller.apply_keep_awake(&mut state);
+            controller.restore_timer(&mut state);
+            // 申请后实际使用的后端可能发生了回退，也可能需要显示警告
+            refresh(&mut tray_icon, &state, &controller.backend_name(), &processes);
+        }
 
         event_rx.iter().for_each(|event| {
-            if event != Event::ShowMenu {
-                let mut state = match event_handler_state.lock() {
-                    Ok(guard) => guard,
-                    Err(e) => {
-                        eprintln!("获取应用状态锁失败: {}", e);
-                        return;
-                    }
-                };
-
-                let mut needs_menu_update = true;
-                
-                match event {
-                    Event::ToggleActive => {
-                        state.is_active = !state.is_active;
-                        set_keep_awake(state.is_active);
-
-                        if state.is_active {
-                            start_timer_thread(&mut state, event_tx.clone());
-                        } else {
-                            stop_timer_thread(&mut state);
-                        }
-                    }
-                    Event::SetDuration(new_duration) => {
-                        state.duration = new_duration;
-                        if state.is_active {
-                            stop_timer_thread(&mut state);
-                            start_timer_thread(&mut state, event_tx.clone());
-                        }
-                    }
-                    Event::ThemeChanged => {
-                        // ThemeChanged 已经需要更新菜单
-                    }
-                    Event::Exit => {
-                        set_keep_awake(false);
-                        std::process::exit(0);
-                    }
-                    Event::NoOp => {
-                        needs_menu_update = false;
-                    }
-                    Event::ShowMenu => unreachable!(),
+            let mut state = match event_handler_state.lock() {
+                Ok(guard) => guard,
+                Err(e) => {
+                    eprintln!("获取应用状态锁失败: {}", e);
+                    return;
                 }
+            };
 
+            match controller.handle_event(&mut state, event) {
+                Flow::Exit => std::process::exit(0),
+                Flow::ShowMenu => {
+                    // 整点选项和进程列表随时间变化，自定义选项可能刚在配置文件中修改过，弹出前重建菜单
+                    reload_menu_config(&mut state);
+                    processes = pickable_processes();
+                    refresh(&mut tray_icon, &state, &controller.backend_name(), &processes);
+                    let _ = tray_icon.show_menu();
+                }
                 // 只在需要时更新菜单
-                if needs_menu_update {
-                    let new_menu = build_menu(state.is_active, state.duration, &state.translations);
-                    if let Err(e) = tray_icon.set_menu(&new_menu) {
-                        eprintln!("更新托盘菜单失败: {}", e);
-                    }
+                Flow::Continue { refresh_menu: true } => {
+                    refresh(&mut tray_icon, &state, &controller.backend_name(), &processes)
                 }
-            } else {
-                let _ = tray_icon.show_menu();
+                Flow::Continue { refresh_menu: false } => {}
             }
         })
     });
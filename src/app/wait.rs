@@ -0,0 +1,141 @@
+//! 等待已有的进程退出
+//!
+//! Linux 上使用 pidfd，打开之后即使 PID 被复用也不会等错进程；内核不支持 pidfd 时退回到定期检查。
+//! Windows 上等待进程句柄。
+
+use super::state::Event;
+use crossbeam_channel::Sender;
+use std::io;
+use std::thread;
+
+/// 打开的进程，用于等待它退出
+pub struct ProcessHandle {
+    pub pid: u32,
+    #[cfg(target_os = "linux")]
+    fd: Option<std::os::fd::OwnedFd>,
+    #[cfg(windows)]
+    handle: std::os::windows::io::OwnedHandle,
+}
+
+impl ProcessHandle {
+    /// 打开进程，进程不存在或没有权限时返回错误
+    #[cfg(target_os = "linux")]
+    pub fn open(pid: u32) -> io::Result<Self> {
+        use std::os::fd::FromRawFd;
+
+        let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+        if fd >= 0 {
+            // SAFETY: pidfd_open 返回的是新打开的描述符，由这里独占
+            let fd = unsafe { std::os::fd::OwnedFd::from_raw_fd(fd as i32) };
+            return Ok(ProcessHandle { pid, fd: Some(fd) });
+        }
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() != Some(libc::ENOSYS) {
+            return Err(e);
+        }
+        // Linux 5.3 之前没有 pidfd
+        if !is_alive(pid) {
+            return Err(io::Error::from_raw_os_error(libc::ESRCH));
+        }
+        Ok(ProcessHandle { pid, fd: None })
+    }
+
+    /// 打开进程，进程不存在或没有权限时返回错误
+    #[cfg(windows)]
+    pub fn open(pid: u32) -> io::Result<Self> {
+        let handle = super::win_api::open_process_for_wait(pid)?;
+        Ok(ProcessHandle { pid, handle })
+    }
+
+    /// 打开进程，进程不存在或没有权限时返回错误
+    #[cfg(not(any(windows, target_os = "linux")))]
+    pub fn open(_pid: u32) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "当前平台不支持等待进程"))
+    }
+
+    /// 阻塞到进程退出
+    #[cfg(target_os = "linux")]
+    pub fn wait(&self) -> io::Result<()> {
+        use std::os::fd::AsRawFd;
+
+        let Some(fd) = &self.fd else {
+            while is_alive(self.pid) {
+                thread::sleep(std::time::Duration::from_secs(1));
+            }
+            return Ok(());
+        };
+        // 进程退出后 pidfd 变为可读
+        let mut pollfd = libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        loop {
+            if unsafe { libc::poll(&mut pollfd, 1, -1) } >= 0 {
+                return Ok(());
+            }
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+    }
+
+    /// 阻塞到进程退出
+    #[cfg(windows)]
+    pub fn wait(&self) -> io::Result<()> {
+        super::win_api::wait_for_handle(&self.handle)
+    }
+
+    /// 阻塞到进程退出
+    #[cfg(not(any(windows, target_os = "linux")))]
+    pub fn wait(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 进程是否仍然存在，没有权限发送信号的进程同样算作存在
+#[cfg(target_os = "linux")]
+fn is_alive(pid: u32) -> bool {
+    let alive = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+    alive || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// 在后台线程中等待进程退出，然后发送 [`Event::ProcessExited`]
+///
+/// 无法打开或等待进程时同样发送，避免一直保持唤醒。
+pub fn watch(pid: u32, event_tx: Sender<Event>) {
+    thread::spawn(move || {
+        if let Err(e) = ProcessHandle::open(pid).and_then(|process| process.wait()) {
+            eprintln!("等待进程 {} 退出失败: {}", pid, e);
+        }
+        let _ = event_tx.send(Event::ProcessExited(pid));
+    });
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn reports_exit_of_watched_process() {
+        let mut child = std::process::Command::new("sleep").arg("0.2").spawn().unwrap();
+        let pid = child.id();
+        let (event_tx, event_rx) = crossbeam_channel::unbounded();
+        watch(pid, event_tx);
+        assert!(event_rx.recv_timeout(Duration::from_millis(50)).is_err());
+
+        // 由测试负责回收子进程，退出后 pidfd 即可读
+        child.wait().unwrap();
+        assert_eq!(event_rx.recv_timeout(Duration::from_secs(5)), Ok(Event::ProcessExited(pid)));
+    }
+
+    #[test]
+    fn missing_process_cannot_be_opened() {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert!(ProcessHandle::open(pid).is_err());
+    }
+}
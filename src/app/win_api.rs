@@ -1,25 +1,55 @@
 //! 封装 Windows API 调用
 
 use super::darkmode;
-use super::state::Event;
+use super::notify;
+use super::rules::{CpuTimes, ProcessInfo};
+use super::state::{Event, KeepMode};
 use crossbeam_channel::Sender;
-use std::ffi::CString;
+use std::ffi::{CString, OsString};
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicPtr, Ordering};
 use std::sync::Mutex;
-use winapi::shared::winerror;
-use winapi::shared::windef::HWND;
-use winapi::um::{errhandlingapi, handleapi, synchapi, winbase, winnt, winuser};
+use winapi::shared::{minwindef, netioapi, winerror};
+use winapi::shared::windef::{HWND, HWND__};
+use winapi::um::{
+    consoleapi, datetimeapi, errhandlingapi, handleapi, libloaderapi, minwinbase, powerbase, powrprof,
+    processthreadsapi, shellapi, synchapi, tlhelp32, winbase, wincon, winnt, winuser,
+};
 
-/// 主题变化通知回调
-static mut THEME_CHANGE_CALLBACK: Option<Mutex<Sender<Event>>> = None;
+/// 消息窗口向事件循环发送事件的通道
+static mut EVENT_SENDER: Option<Mutex<Sender<Event>>> = None;
 
-/// 设置主题变化回调
-pub fn set_theme_change_callback(sender: Sender<Event>) {
+/// 消息窗口的句柄，显示通知气泡时使用
+static MESSAGE_WINDOW: AtomicPtr<HWND__> = AtomicPtr::new(std::ptr::null_mut());
+
+/// 通知气泡图标发回的回调消息
+const WM_BALLOON_CALLBACK: u32 = winuser::WM_APP + 1;
+
+/// 通知气泡使用的托盘图标 id，与托盘图标分开，只在显示气泡期间存在
+const BALLOON_ICON_ID: u32 = 1;
+
+/// 设置消息窗口发送事件的通道
+pub fn set_event_sender(sender: Sender<Event>) {
     unsafe {
-        let ptr = std::ptr::addr_of_mut!(THEME_CHANGE_CALLBACK);
+        let ptr = std::ptr::addr_of_mut!(EVENT_SENDER);
         *ptr = Some(Mutex::new(sender));
     }
 }
 
+/// 通过消息窗口发送事件
+fn send_event(event: Event) {
+    // SAFETY: 只在启动时写入一次，之后只读
+    unsafe {
+        let ptr = std::ptr::addr_of!(EVENT_SENDER);
+        if let Some(ref sender) = *ptr
+            && let Ok(sender) = sender.lock() {
+                let _ = sender.send(event);
+            }
+    }
+}
+
 /// 创建一个命名互斥锁以确保只有一个实例在运行。
 pub fn create_single_instance_mutex() -> bool {
     let mutex_name = match CString::new("KeepScreenAppMutex") {
@@ -45,22 +75,314 @@ pub fn create_single_instance_mutex() -> bool {
     true
 }
 
-/// 设置系统的执行状态以保持亮屏
-pub fn set_keep_awake(awake: bool) {
+/// 设置系统的执行状态以保持亮屏，`None` 表示恢复默认
+pub fn set_keep_awake(mode: Option<KeepMode>) -> std::io::Result<()> {
+    let mut flags = winnt::ES_CONTINUOUS;
+    if let Some(mode) = mode {
+        if mode.keeps_system() {
+            flags |= winnt::ES_SYSTEM_REQUIRED;
+        }
+        if mode.keeps_display() {
+            flags |= winnt::ES_DISPLAY_REQUIRED;
+        }
+    }
+    // 返回值为之前的执行状态，为 0 表示调用失败
+    if unsafe { winbase::SetThreadExecutionState(flags) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// 读取系统当前汇总的执行状态，检查是否包含指定模式需要的标志
+pub fn is_keep_awake_effective(mode: KeepMode) -> std::io::Result<bool> {
+    let mut state: winnt::EXECUTION_STATE = 0;
+    let status = unsafe {
+        powerbase::CallNtPowerInformation(
+            winnt::SystemExecutionState,
+            std::ptr::null_mut(),
+            0,
+            (&mut state as *mut winnt::EXECUTION_STATE).cast(),
+            std::mem::size_of::<winnt::EXECUTION_STATE>() as u32,
+        )
+    };
+    if status != 0 {
+        return Err(std::io::Error::other(format!("CallNtPowerInformation 返回 {:#x}", status)));
+    }
+    let system_ok = !mode.keeps_system() || state & winnt::ES_SYSTEM_REQUIRED != 0;
+    let display_ok = !mode.keeps_display() || state & winnt::ES_DISPLAY_REQUIRED != 0;
+    Ok(system_ok && display_ok)
+}
+
+/// 用关联的默认程序打开文件
+pub fn shell_open(path: &Path) -> std::io::Result<()> {
+    let wide = |s: &std::ffi::OsStr| s.encode_wide().chain(std::iter::once(0)).collect::<Vec<u16>>();
+    let verb = wide("open".as_ref());
+    let file = wide(path.as_os_str());
+    let result = unsafe {
+        shellapi::ShellExecuteW(
+            std::ptr::null_mut(),
+            verb.as_ptr(),
+            file.as_ptr(),
+            std::ptr::null(),
+            std::ptr::null(),
+            winuser::SW_SHOWNORMAL,
+        )
+    };
+    // 返回值大于 32 表示成功
+    if result as usize <= 32 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// 把字符串复制到定长的宽字符数组，超出部分截断
+fn copy_wide(dst: &mut [u16], text: &str) {
+    let wide: Vec<u16> = text.encode_utf16().take(dst.len() - 1).collect();
+    dst[..wide.len()].copy_from_slice(&wide);
+    dst[wide.len()] = 0;
+}
+
+/// 通知气泡图标的数据
+fn balloon_icon_data(hwnd: HWND) -> shellapi::NOTIFYICONDATAW {
+    // SAFETY: 纯数据结构体，全零是合法的初始值
+    let mut data: shellapi::NOTIFYICONDATAW = unsafe { std::mem::zeroed() };
+    data.cbSize = std::mem::size_of::<shellapi::NOTIFYICONDATAW>() as u32;
+    data.hWnd = hwnd;
+    data.uID = BALLOON_ICON_ID;
+    data
+}
+
+/// 显示通知气泡
+///
+/// 托盘库没有提供气泡提示，因此由消息窗口临时添加一个托盘图标来显示，
+/// 气泡消失后移除。用户点击气泡时发送延长事件。
+pub fn show_balloon(title: &str, text: &str) -> std::io::Result<()> {
+    let hwnd = MESSAGE_WINDOW.load(Ordering::SeqCst);
+    if hwnd.is_null() {
+        return Err(std::io::Error::other("消息窗口尚未创建"));
+    }
+    let mut data = balloon_icon_data(hwnd);
+    data.uFlags = shellapi::NIF_ICON | shellapi::NIF_MESSAGE | shellapi::NIF_INFO | shellapi::NIF_TIP;
+    data.uCallbackMessage = WM_BALLOON_CALLBACK;
+    data.dwInfoFlags = shellapi::NIIF_INFO;
+    // 资源中的应用图标，由 build.rs 嵌入
+    data.hIcon = unsafe {
+        winuser::LoadIconW(
+            libloaderapi::GetModuleHandleW(std::ptr::null()),
+            winuser::MAKEINTRESOURCEW(1),
+        )
+    };
+    copy_wide(&mut data.szTip, title);
+    copy_wide(&mut data.szInfoTitle, title);
+    copy_wide(&mut data.szInfo, text);
+    // 已有气泡时直接替换内容
     unsafe {
-        if awake {
-            winbase::SetThreadExecutionState(winnt::ES_SYSTEM_REQUIRED | winnt::ES_DISPLAY_REQUIRED | winnt::ES_CONTINUOUS);
-        } else {
-            winbase::SetThreadExecutionState(winnt::ES_CONTINUOUS);
+        if shellapi::Shell_NotifyIconW(shellapi::NIM_MODIFY, &mut data) == 0
+            && shellapi::Shell_NotifyIconW(shellapi::NIM_ADD, &mut data) == 0
+        {
+            return Err(std::io::Error::last_os_error());
         }
     }
+    Ok(())
+}
+
+/// 关闭通知气泡并移除临时图标
+pub fn hide_balloon() {
+    let hwnd = MESSAGE_WINDOW.load(Ordering::SeqCst);
+    if hwnd.is_null() {
+        return;
+    }
+    let mut data = balloon_icon_data(hwnd);
+    unsafe {
+        shellapi::Shell_NotifyIconW(shellapi::NIM_DELETE, &mut data);
+    }
+}
+
+/// 锁定工作站
+pub fn lock_workstation() -> std::io::Result<()> {
+    if unsafe { winuser::LockWorkStation() } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// 关闭显示器，移动鼠标或按键后自动恢复
+pub fn turn_off_display() -> std::io::Result<()> {
+    // 用 PostMessage 广播，避免被无响应的窗口阻塞；参数 2 表示关闭
+    if unsafe { winuser::PostMessageW(winuser::HWND_BROADCAST, winuser::WM_SYSCOMMAND, winuser::SC_MONITORPOWER, 2) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// 让系统进入睡眠
+pub fn suspend() -> std::io::Result<()> {
+    if unsafe { powrprof::SetSuspendState(0, 0, 0) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// 连接到启动本程序的控制台，发布版本使用 GUI 子系统，默认没有控制台
+pub fn attach_parent_console() {
+    unsafe { wincon::AttachConsole(wincon::ATTACH_PARENT_PROCESS) };
+}
+
+/// 忽略控制台的 Ctrl+C，子进程仍会收到
+pub fn ignore_console_interrupts() {
+    unsafe { consoleapi::SetConsoleCtrlHandler(None, 1) };
+}
+
+/// 通过 toolhelp 快照列出正在运行的进程
+///
+/// 查询路径需要逐个打开进程，`with_paths` 为 false 时跳过，路径均为 `None`。
+pub fn list_processes(with_paths: bool) -> std::io::Result<Vec<ProcessInfo>> {
+    let snapshot = unsafe { tlhelp32::CreateToolhelp32Snapshot(tlhelp32::TH32CS_SNAPPROCESS, 0) };
+    if snapshot == handleapi::INVALID_HANDLE_VALUE {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut processes = Vec::new();
+    let mut entry: tlhelp32::PROCESSENTRY32W = unsafe { std::mem::zeroed() };
+    entry.dwSize = std::mem::size_of::<tlhelp32::PROCESSENTRY32W>() as u32;
+    let mut ok = unsafe { tlhelp32::Process32FirstW(snapshot, &mut entry) };
+    while ok != 0 {
+        let len = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len());
+        processes.push(ProcessInfo {
+            pid: entry.th32ProcessID,
+            name: String::from_utf16_lossy(&entry.szExeFile[..len]),
+            path: with_paths.then(|| process_path(entry.th32ProcessID)).flatten(),
+        });
+        ok = unsafe { tlhelp32::Process32NextW(snapshot, &mut entry) };
+    }
+    unsafe { handleapi::CloseHandle(snapshot) };
+    Ok(processes)
+}
+
+/// 进程所在的登录会话，服务进程在会话 0 中
+pub fn process_session_id(pid: u32) -> Option<u32> {
+    let mut session = 0;
+    (unsafe { processthreadsapi::ProcessIdToSessionId(pid, &mut session) } != 0).then_some(session)
+}
+
+/// 进程可执行文件的完整路径，没有权限打开进程时为 `None`
+fn process_path(pid: u32) -> Option<PathBuf> {
+    let process = unsafe { processthreadsapi::OpenProcess(winnt::PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if process.is_null() {
+        return None;
+    }
+    let mut buffer = [0u16; 1024];
+    let mut len = buffer.len() as u32;
+    let ok = unsafe { winbase::QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut len) };
+    unsafe { handleapi::CloseHandle(process) };
+    (ok != 0).then(|| PathBuf::from(OsString::from_wide(&buffer[..len as usize])))
+}
+
+/// 打开进程以等待它退出
+pub fn open_process_for_wait(pid: u32) -> std::io::Result<OwnedHandle> {
+    let process = unsafe { processthreadsapi::OpenProcess(winnt::SYNCHRONIZE, 0, pid) };
+    if process.is_null() {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: OpenProcess 返回的是新打开的句柄，由调用方独占
+    Ok(unsafe { OwnedHandle::from_raw_handle(process as _) })
+}
+
+/// 阻塞到句柄变为有信号，对进程句柄而言就是进程退出
+pub fn wait_for_handle(handle: &OwnedHandle) -> std::io::Result<()> {
+    match unsafe { synchapi::WaitForSingleObject(handle.as_raw_handle() as _, winbase::INFINITE) } {
+        winbase::WAIT_FAILED => Err(std::io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// 所有硬件网卡收发字节数之和，不含回环和虚拟接口
+pub fn network_bytes() -> std::io::Result<u64> {
+    let mut table: netioapi::PMIB_IF_TABLE2 = std::ptr::null_mut();
+    let result = unsafe { netioapi::GetIfTable2(&mut table) };
+    if result != winerror::NO_ERROR {
+        return Err(std::io::Error::from_raw_os_error(result as i32));
+    }
+    // SAFETY: GetIfTable2 成功时返回 NumEntries 行，用完后由 FreeMibTable 释放
+    let rows = unsafe { std::slice::from_raw_parts((*table).Table.as_ptr(), (*table).NumEntries as usize) };
+    let total = rows
+        .iter()
+        .filter(|row| {
+            let flags = row.InterfaceAndOperStatusFlags;
+            flags.HardwareInterface() != 0 && flags.FilterInterface() == 0
+        })
+        .map(|row| row.InOctets + row.OutOctets)
+        .sum();
+    unsafe { netioapi::FreeMibTable(table as _) };
+    Ok(total)
+}
+
+/// 自开机以来所有 CPU 的累计时间，内核时间中已经包含空闲时间
+pub fn cpu_times() -> std::io::Result<CpuTimes> {
+    let mut idle: minwindef::FILETIME = unsafe { std::mem::zeroed() };
+    let mut kernel: minwindef::FILETIME = unsafe { std::mem::zeroed() };
+    let mut user: minwindef::FILETIME = unsafe { std::mem::zeroed() };
+    if unsafe { processthreadsapi::GetSystemTimes(&mut idle, &mut kernel, &mut user) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let ticks = |time: minwindef::FILETIME| (time.dwHighDateTime as u64) << 32 | time.dwLowDateTime as u64;
+    Ok(CpuTimes {
+        idle: ticks(idle),
+        total: ticks(kernel) + ticks(user),
+    })
+}
+
+/// 按用户在区域设置中选择的短时间格式显示时间，不含秒
+pub fn format_time(hour: u32, minute: u32) -> Option<String> {
+    /// `GetTimeFormatEx` 不显示秒的标志，winapi 中没有定义
+    const TIME_NOSECONDS: u32 = 0x2;
+    let time = minwinbase::SYSTEMTIME {
+        wHour: hour as u16,
+        wMinute: minute as u16,
+        ..unsafe { std::mem::zeroed() }
+    };
+    let mut buffer = [0u16; 64];
+    // 区域名称为空表示当前用户的默认区域
+    let len = unsafe {
+        datetimeapi::GetTimeFormatEx(
+            std::ptr::null(),
+            TIME_NOSECONDS,
+            &time,
+            std::ptr::null(),
+            buffer.as_mut_ptr(),
+            buffer.len() as i32,
+        )
+    };
+    // 返回的长度包含结尾的空字符
+    (len > 1).then(|| String::from_utf16_lossy(&buffer[..len as usize - 1]))
+}
+
+/// 发送一次零距离的鼠标移动，重置系统的空闲计时
+pub fn send_idle_input() -> std::io::Result<()> {
+    let mut input = winuser::INPUT {
+        type_: winuser::INPUT_MOUSE,
+        u: unsafe { std::mem::zeroed() },
+    };
+    // SAFETY: 联合体按鼠标输入解释，其余字段保持为零
+    unsafe {
+        input.u.mi_mut().dwFlags = winuser::MOUSEEVENTF_MOVE;
+    }
+    let size = std::mem::size_of::<winuser::INPUT>() as i32;
+    if unsafe { winuser::SendInput(1, &mut input, size) } != 1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
 }
 
 /// Windows 消息循环
 pub fn message_loop() {
     unsafe {
         // 创建隐藏窗口用于监听系统消息
-        create_message_window();
+        let hwnd = create_message_window();
+        MESSAGE_WINDOW.store(hwnd, Ordering::SeqCst);
+        // 消息窗口收不到广播，需要单独订阅睡眠唤醒通知
+        if winuser::RegisterSuspendResumeNotification(hwnd as _, winuser::DEVICE_NOTIFY_WINDOW_HANDLE).is_null() {
+            eprintln!("订阅睡眠唤醒通知失败: {}", std::io::Error::last_os_error());
+        }
 
         let mut msg = std::mem::MaybeUninit::uninit();
         loop {
@@ -129,14 +451,24 @@ unsafe extern "system" fn message_window_proc(
             // 处理系统设置变化消息
             if darkmode::handle_setting_change(lparam) {
                 // 通知应用主题已变化
-                // SAFETY: 访问静态变量
-                unsafe {
-                    let ptr = std::ptr::addr_of!(THEME_CHANGE_CALLBACK);
-                    if let Some(ref callback) = *ptr
-                        && let Ok(sender) = callback.lock() {
-                            let _ = sender.send(Event::ThemeChanged);
-                        }
+                send_event(Event::ThemeChanged);
+            }
+            0
+        }
+        winuser::WM_POWERBROADCAST => {
+            if wparam == winuser::PBT_APMRESUMEAUTOMATIC {
+                send_event(Event::Resumed);
+            }
+            1
+        }
+        WM_BALLOON_CALLBACK => {
+            match lparam as u32 {
+                shellapi::NIN_BALLOONUSERCLICK => {
+                    send_event(notify::EXTEND_EVENT);
+                    hide_balloon();
                 }
+                shellapi::NIN_BALLOONTIMEOUT | shellapi::NIN_BALLOONHIDE => hide_balloon(),
+                _ => {}
             }
             0
         }
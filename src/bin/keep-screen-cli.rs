@@ -0,0 +1,6 @@
+// Console-subsystem build of the command-line mode, so that cmd.exe and
+// PowerShell wait for it and receive the exit code.
+
+fn main() {
+    keep_screen::app::run_cli();
+}
@@ -0,0 +1,3 @@
+//! Keep Screen 的全部功能，托盘程序和命令行程序共用
+
+pub mod app;
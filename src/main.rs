@@ -1,8 +1,6 @@
 // Hides the console window on Windows in release builds.
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod app;
-
 fn main() {
-    app::run();
-}
\ No newline at end of file
+    keep_screen::app::run();
+}
@@ -8,10 +8,10 @@ fn main() {
     // Simple argument parsing to find a --target flag.
     let args: Vec<String> = env::args().collect();
     let mut target: Option<&str> = None;
-    if let Some(index) = args.iter().position(|r| r == "--target") {
-        if let Some(t) = args.get(index + 1) {
-            target = Some(t);
-        }
+    if let Some(index) = args.iter().position(|r| r == "--target")
+        && let Some(t) = args.get(index + 1)
+    {
+        target = Some(t);
     }
 
     // 1. Compile the main project in release mode, passing the target if it exists.
@@ -49,4 +49,13 @@ fn main() {
         eprintln!("Compiled exe not found: {}", exe_src.display());
         std::process::exit(1);
     }
+
+    // 3. The console build of the command-line mode is shipped next to it under its own name.
+    let cli_exe = target_dir.join("keep-screen-cli.exe");
+    if cli_exe.exists() {
+        println!("Command-line build: {}", cli_exe.display());
+    } else {
+        eprintln!("Compiled exe not found: {}", cli_exe.display());
+        std::process::exit(1);
+    }
 }